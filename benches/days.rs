@@ -0,0 +1,32 @@
+// Benchmarks every full (non-sample) input in the answer manifest, timing parsing and each part separately.
+// Compare against a saved baseline with:
+//   cargo bench --bench days -- --save-baseline before
+//   cargo bench --bench days -- --baseline before
+// and narrow to some days with a filter, e.g. `cargo bench --bench days -- day05`.
+
+use std::collections::HashSet;
+
+use advent_2023::{solution, manifest::{self, Manifest}};
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+fn bench_days(c: &mut Criterion) {
+  let manifest = Manifest::load(manifest::DEFAULT_PATH).unwrap();
+  let mut parsed_inputs = HashSet::new();
+  for check in manifest.checks.iter().filter(|check| !check.is_sample()) {
+    let sol = solution(check.day).unwrap();
+    let input = check.read_input().unwrap();
+    let params = check.params();
+
+    let mut group = c.benchmark_group(format!("day{:02}", check.day));
+    group.sample_size(10);
+    if parsed_inputs.insert((check.day, check.input.clone())) {
+      group.bench_function("parse", |b| b.iter_with_large_drop(|| sol.parse(black_box(&input)).unwrap()));
+    }
+    let parsed = sol.parse(&input).unwrap();
+    group.bench_function(format!("part{}", check.part), |b| b.iter(|| sol.solve(parsed.as_ref(), check.part, &params).unwrap()));
+    group.finish();
+  }
+}
+
+criterion_group!(benches, bench_days);
+criterion_main!(benches);
@@ -0,0 +1,22 @@
+use std::{env, process::exit};
+
+use advent_2023::generate::generate;
+
+const USAGE: &str = "usage: gen <day> <size> [seed]";
+
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  if !(2..=3).contains(&args.len()) { fail(USAGE); }
+  let day: u8 = args[0].parse().unwrap_or_else(|_| fail(&format!("invalid day {:?}", args[0])));
+  let size: usize = args[1].parse().unwrap_or_else(|_| fail(&format!("invalid size {:?}", args[1])));
+  let seed: u64 = args.get(2).map_or(Ok(0), |seed| seed.parse())
+    .unwrap_or_else(|_| fail(&format!("invalid seed {:?}", args[2])));
+
+  let input = generate(day, size, seed).unwrap_or_else(|| fail(&format!("no generator for day {}", day)));
+  print!("{}", input);
+}
+
+fn fail(msg: &str) -> ! {
+  eprintln!("{}", msg);
+  exit(1);
+}
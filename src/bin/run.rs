@@ -0,0 +1,36 @@
+use std::{env, fs::File, io::{stdin, BufReader}, process::exit, time::Instant};
+
+use advent_2023::{solution, solution::read_input};
+
+const USAGE: &str = "usage: run <day> <part> [input|-] [name=value...]";
+
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  if args.len() < 2 { fail(USAGE); }
+  let day: u8 = args[0].parse().unwrap_or_else(|_| fail(&format!("invalid day {:?}", args[0])));
+  let part: u8 = args[1].parse().unwrap_or_else(|_| fail(&format!("invalid part {:?}", args[1])));
+  let sol = solution(day).unwrap_or_else(|| fail(&format!("no solution for day {}", day)));
+
+  let input = match args.get(2).map(String::as_str) {
+    None | Some("-") => read_input(stdin().lock()).unwrap_or_else(|e| fail(&format!("stdin: {}", e))),
+    Some(path) => File::open(path)
+      .map_err(Into::into)
+      .and_then(|file| read_input(BufReader::new(file)))
+      .unwrap_or_else(|e| fail(&format!("{}: {}", path, e))),
+  };
+  let params: Vec<_> = args.get(3..).unwrap_or_default().iter()
+    .map(|arg| arg.split_once('=').unwrap_or_else(|| fail(&format!("expected name=value, got {:?}\n{}", arg, USAGE))))
+    .collect();
+
+  let start = Instant::now();
+  let answer = sol.run(&input, part, &params).unwrap_or_else(|e| fail(&e.to_string()));
+  let elapsed = start.elapsed();
+
+  println!("{}", answer);
+  eprintln!("day {:02} part {} solved in {:?}", day, part, elapsed);
+}
+
+fn fail(msg: &str) -> ! {
+  eprintln!("{}", msg);
+  exit(1);
+}
@@ -0,0 +1,40 @@
+use std::{env, process::exit, time::Duration};
+
+use advent_2023::manifest::{self, Manifest};
+
+fn main() {
+  let mut paths: Vec<String> = env::args().skip(1).collect();
+  if paths.is_empty() {
+    paths.push(manifest::DEFAULT_PATH.to_string());
+  }
+
+  println!("{:>3} {:>4}  {:<24} {:>16} {:>16} {:>12}  result", "day", "part", "input", "expected", "answer", "time");
+  let mut passed = 0;
+  let mut failed = 0;
+  let mut total_time = Duration::ZERO;
+  for path in &paths {
+    let manifest = Manifest::load(path).unwrap_or_else(|e| {
+      eprintln!("{}", e);
+      exit(2);
+    });
+    for check in &manifest.checks {
+      let outcome = check.run();
+      let (answer, time, result) = match &outcome {
+        Ok((answer, elapsed)) => {
+          total_time += *elapsed;
+          let result = if *answer == check.expected() { "pass".to_string() } else { "FAIL".to_string() };
+          (answer.to_string(), format!("{:.2?}", elapsed), result)
+        },
+        Err(e) => ("-".to_string(), "-".to_string(), format!("FAIL: {}", e)),
+      };
+      if result == "pass" { passed += 1; } else { failed += 1; }
+      println!("{:>3} {:>4}  {:<24} {:>16} {:>16} {:>12}  {}",
+        check.day, check.part, check.input.display(), check.expected(), answer, time, result);
+    }
+  }
+  println!("{} passed, {} failed, {:.2?} total", passed, failed, total_time);
+
+  if failed > 0 {
+    exit(1);
+  }
+}
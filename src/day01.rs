@@ -1,90 +1,353 @@
-pub fn part1(lines: impl Iterator<Item=String>) -> u32 {
-  lines
-    .map(|line| read_line(&line, false))
-    .sum()
-}
-pub fn part2(lines: impl Iterator<Item=String>) -> u32 {
-  lines
-    .map(|line| read_line(&line, true))
-    .sum()
-}
-
-fn read_line(line: &str, allow_text: bool) -> u32 {
-  let mut char_idxs = line.char_indices();
-  let first_digit;
-  let mut last_digit;
-  'first: {
-    while let Some((i, _)) = char_idxs.next() {
-      if let Some(digit) = read_digit(&line[i..], allow_text) {
-        first_digit = digit;
-        last_digit = digit;
-        break 'first;
-      }
+use std::{cmp::Reverse, collections::{HashMap, VecDeque}, fmt::{self, Display}, io::BufRead, ops::Range};
+
+use crate::{solution::{self, read_input, joined_lines, parse_param_pairs, unknown_param, Solution, Answer}, error::{Error, Result}};
+
+pub fn part1(input: impl BufRead) -> Result<u32> { calibration_sum(read_input(input)?.lines(), &Vocabulary::digits()) }
+pub fn part2(input: impl BufRead) -> Result<u32> { calibration_sum(read_input(input)?.lines(), &Vocabulary::english()) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<u32> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<u32> { part2(joined_lines(lines)) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 1;
+  type Input = String;
+  type Params = Params;
+
+  fn parse(input: &str) -> Result<Self::Input> { Ok(input.to_owned()) }
+  fn part1(text: &Self::Input, _: &Params) -> Result<Answer> { calibration_sum(text.lines(), &Vocabulary::digits()).map(Answer::from) }
+  fn part2(text: &Self::Input, params: &Params) -> Result<Answer> { calibration_sum(text.lines(), &params.vocab).map(Answer::from) }
+}
+
+// Part 2's vocabulary. The `words` parameter replaces the English words with a list like "un=1,deux=2"
+pub struct Params {
+  pub vocab: Vocabulary,
+}
+
+impl Default for Params {
+  fn default() -> Self { Self { vocab: Vocabulary::english() } }
+}
+
+impl solution::Params for Params {
+  const NAMES: &'static [&'static str] = &["words"];
+
+  fn set(&mut self, name: &str, value: &str) -> Result<()> {
+    match name {
+      "words" => self.vocab = Vocabulary::with_digits(parse_param_pairs(name, value)?)?,
+      _ => return unknown_param(name),
+    }
+    Ok(())
+  }
+}
+
+// Sums the calibration values of all lines, or reports every line that has none
+pub fn calibration_sum(lines: impl Iterator<Item=impl AsRef<str>>, vocab: &Vocabulary) -> Result<u32> {
+  let mut sum = 0;
+  let mut missing = vec![];
+  for (i, line) in lines.enumerate() {
+    match vocab.calibration_value(line.as_ref()) {
+      Some(value) => sum = u32::checked_add(sum, value)
+        .ok_or_else(|| Error::input(Solver::DAY, "calibration values add up past 32 bits"))?,
+      None => missing.push((i, line)),
     }
-    panic!();
   }
-  while let Some((i, _)) = char_idxs.next() {
-    if let Some(digit) = read_digit(&line[i..], allow_text) {
-      last_digit = digit;
+  match missing.split_first() {
+    None => Ok(sum),
+    Some(((i, line), [])) => Err(Error::parse(Solver::DAY, *i, line.as_ref(), 0, "no digits")),
+    Some(((i, line), rest)) => {
+      let others: Vec<_> = rest.iter().map(|(i, _)| (i + 1).to_string()).collect();
+      Err(Error::parse(Solver::DAY, *i, line.as_ref(), 0, format!("no digits (nor on lines {})", others.join(", "))))
+    },
+  }
+}
+
+// How each line's calibration value was read, for checking disputed documents. Displays as the line with the first
+// and last tokens marked beneath it
+pub struct LineReport<'a> {
+  pub line: &'a str,
+  pub ends: Option<(Match, Match)>,
+  pub value: Option<u32>,
+}
+
+pub fn report<'a>(text: &'a str, vocab: &Vocabulary) -> Vec<LineReport<'a>> {
+  text.lines()
+    .map(|line| {
+      let ends = vocab.first_and_last(line);
+      LineReport { line, ends, value: ends.and_then(|(first, last)| concat(first.value, last.value)) }
+    })
+    .collect()
+}
+
+impl Display for LineReport<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let (Some((first, last)), Some(value)) = (self.ends, self.value) else {
+      return write!(f, "{} => no digits", self.line);
+    };
+    write!(f, "{} => {}", self.line, value)?;
+    let tokens = if first == last { &[first][..] } else { &[first, last][..] };
+    for token in tokens {
+      let col = self.line[..token.start].chars().count();
+      let width = self.line[token.span()].chars().count();
+      write!(f, "\n{}{} {} {} ({}..{})", " ".repeat(col), "^".repeat(width), token.kind, token.value, token.start, token.start + token.len)?;
     }
+    Ok(())
   }
-  first_digit*10 + last_digit
-}
-
-fn read_digit(from: &str, allow_text: bool) -> Option<u32> {
-  let mut chars = from.chars();
-  let first_char = chars.next()?;
-  if let Some(first_digit) = first_char.to_digit(10) {
-    Some(first_digit)
-  } else if allow_text {
-    match first_char {
-      'o' => if chars.take(2).eq("ne".chars()) { Some(1) } else { None },
-      't' => match chars.next()? {
-        'w' => if chars.take(1).eq("o".chars()) { Some(2) } else { None },
-        'h' => if chars.take(3).eq("ree".chars()) { Some(3) } else { None },
-        _ => None,
-      },
-      'f' => match chars.next()? {
-        'o' => if chars.take(2).eq("ur".chars()) { Some(4) } else { None },
-        'i' => if chars.take(2).eq("ve".chars()) { Some(5) } else { None },
-        _ => None,
-      },
-      's' => match chars.next()? {
-        'i' => if chars.take(1).eq("x".chars()) { Some(6) } else { None },
-        'e' => if chars.take(3).eq("ven".chars()) { Some(7) } else { None },
-        _ => None,
-      },
-      'e' => if chars.take(4).eq("ight".chars()) { Some(8) } else { None },
-      'n' => if chars.take(3).eq("ine".chars()) { Some(9) } else { None },
-      _ => None,
+}
+
+// A table of words and the numbers they stand for, compiled into an Aho-Corasick automaton so that one pass over a line
+// finds every occurrence of every word, including overlapping ones like the "eight" and "two" in "eightwo"
+pub struct Vocabulary {
+  nodes: Vec<Node>,
+}
+
+#[derive(Default)]
+struct Node {
+  next: HashMap<char, usize>,
+  // The longest proper suffix of this node's text that is also in the trie
+  fail: usize,
+  // The word spelt by this node's text, as its length in bytes, its value and whether it is a digit
+  word: Option<(usize, u32, TokenKind)>,
+  // The nearest node along the failure chain that ends a word
+  dict: Option<usize>,
+}
+
+// A word found in a line, starting `start` bytes in
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Match {
+  pub start: usize,
+  pub len: usize,
+  pub value: u32,
+  pub kind: TokenKind,
+}
+
+impl Match {
+  pub fn span(&self) -> Range<usize> {
+    self.start..self.start + self.len
+  }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum TokenKind {
+  Digit, Word,
+}
+
+impl Display for TokenKind {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.write_str(match self { Self::Digit => "digit", Self::Word => "word" })
+  }
+}
+
+const ENGLISH: [&str; 9] = ["one", "two", "three", "four", "five", "six", "seven", "eight", "nine"];
+
+impl Vocabulary {
+  pub fn new(words: impl IntoIterator<Item=(String, u32)>) -> Result<Self> {
+    let mut nodes = vec![Node::default()];
+    let mut max_value = 0;
+    for (word, value) in words {
+      if word.is_empty() {
+        return Err(Error::Usage("vocabulary words must not be empty".to_owned()));
+      }
+      let mut node = 0;
+      for c in word.chars() {
+        node = match nodes[node].next.get(&c) {
+          Some(&next) => next,
+          None => {
+            nodes.push(Node::default());
+            let next = nodes.len() - 1;
+            nodes[node].next.insert(c, next);
+            next
+          },
+        };
+      }
+      match nodes[node].word {
+        Some((_, old, _)) if old != value =>
+          return Err(Error::Usage(format!("vocabulary word {:?} has values {} and {}", word, old, value))),
+        _ => {
+          let kind = if word.len() == 1 && word.as_bytes()[0].is_ascii_digit() { TokenKind::Digit } else { TokenKind::Word };
+          nodes[node].word = Some((word.len(), value, kind));
+        },
+      }
+      max_value = max_value.max(value);
+    }
+    // concat grows with both values, so if the largest pair fits then every line's calibration value does
+    if concat(max_value, max_value).is_none() {
+      return Err(Error::Usage(format!("vocabulary value {} is too large to make calibration values of 32 bits", max_value)));
+    }
+
+    // Breadth first, so that every failure link points at a node that has already been linked
+    let mut to_visit: VecDeque<_> = nodes[0].next.values().copied().collect();
+    while let Some(node) = to_visit.pop_front() {
+      let edges: Vec<_> = nodes[node].next.iter().map(|(&c, &child)| (c, child)).collect();
+      for (c, child) in edges {
+        let mut fail = nodes[node].fail;
+        while fail != 0 && !nodes[fail].next.contains_key(&c) {
+          fail = nodes[fail].fail;
+        }
+        let fail = nodes[fail].next.get(&c).copied().filter(|&fail| fail != child).unwrap_or(0);
+        nodes[child].fail = fail;
+        nodes[child].dict = if nodes[fail].word.is_some() { Some(fail) } else { nodes[fail].dict };
+        to_visit.push_back(child);
+      }
     }
-  } else {
-    None
+    Ok(Self { nodes })
   }
+
+  pub fn digits() -> Self {
+    Self::with_digits([]).unwrap()
+  }
+
+  pub fn english() -> Self {
+    Self::with_digits(ENGLISH.iter().zip(1..).map(|(&word, value)| (word.to_owned(), value))).unwrap()
+  }
+
+  // The given words as well as the digit characters
+  pub fn with_digits(words: impl IntoIterator<Item=(String, u32)>) -> Result<Self> {
+    Self::new((0..10).map(|n| (n.to_string(), n)).chain(words))
+  }
+
+  // Every occurrence of every word, in order of where they end
+  pub fn matches<'a>(&'a self, line: &'a str) -> impl Iterator<Item=Match> + 'a {
+    let mut node = 0;
+    line.char_indices().flat_map(move |(i, c)| {
+      while node != 0 && !self.nodes[node].next.contains_key(&c) {
+        node = self.nodes[node].fail;
+      }
+      node = self.nodes[node].next.get(&c).copied().unwrap_or(0);
+      let end = i + c.len_utf8();
+      let first = if self.nodes[node].word.is_some() { Some(node) } else { self.nodes[node].dict };
+      std::iter::successors(first, |&found| self.nodes[found].dict)
+        .map(move |found| {
+          let (len, value, kind) = self.nodes[found].word.unwrap();
+          Match { start: end - len, len, value, kind }
+        })
+    })
+  }
+
+  // The first and last words of the line, with the longer word winning where two start together
+  pub fn first_and_last(&self, line: &str) -> Option<(Match, Match)> {
+    self.matches(line).fold(None, |ends, m| match ends {
+      None => Some((m, m)),
+      Some((first, last)) => Some((
+        if (m.start, Reverse(m.len)) < (first.start, Reverse(first.len)) { m } else { first },
+        if (m.start, m.len) > (last.start, last.len) { m } else { last },
+      )),
+    })
+  }
+
+  pub fn calibration_value(&self, line: &str) -> Option<u32> {
+    let (first, last) = self.first_and_last(line)?;
+    concat(first.value, last.value)
+  }
+}
+
+// The two values written one after the other, so 10 then 3 gives 103, or None if that does not fit
+fn concat(first: u32, last: u32) -> Option<u32> {
+  let shift = 10u128.pow(last.checked_ilog10().unwrap_or(0) + 1);
+  (u128::from(first) * shift + u128::from(last)).try_into().ok()
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::{solution::Params as _, test_util::sample_input};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("01a")), 142);
+    assert_eq!(part1(sample_input("01a")), Ok(142));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("01")), 52974);
+    assert_eq!(part1(sample_input("01")), Ok(52974));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("01b")), 281);
+    assert_eq!(part2(sample_input("01b")), Ok(281));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("01")), 53340);
+    assert_eq!(part2(sample_input("01")), Ok(53340));
+  }
+
+  #[test]
+  fn test_no_digits() {
+    let err = part1("a1b\nabc\n".as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 1, line 2, column 1: no digits at \"abc\"");
+    let err = part1("abc\na1b\nd\ne\n".as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 1, line 1, column 1: no digits (nor on lines 3, 4) at \"abc\"");
+  }
+
+  #[test]
+  fn test_overlaps() {
+    let english = Vocabulary::english();
+    assert_eq!(english.calibration_value("eightwo"), Some(82));
+    assert_eq!(english.calibration_value("oneight"), Some(18));
+    assert_eq!(english.calibration_value("xtwone3four"), Some(24));
+    let starts: Vec<_> = english.matches("twoneight").map(|m| (m.start, m.value)).collect();
+    assert_eq!(starts, [(0, 2), (2, 1), (4, 8)]);
+  }
+
+  #[test]
+  fn test_custom_words() {
+    let words = [("zero", 0), ("ten", 10), ("tent", 7), ("deux", 2), ("trois", 3)]
+      .map(|(word, value)| (word.to_owned(), value));
+    let vocab = Vocabulary::with_digits(words).unwrap();
+    assert_eq!(vocab.calibration_value("ten3"), Some(103));
+    assert_eq!(vocab.calibration_value("deuxzero"), Some(20));
+    assert_eq!(vocab.calibration_value("tenxten"), Some(1010));
+    // "tent" beats "ten" at the same start
+    assert_eq!(vocab.calibration_value("xtentrois"), Some(73));
+    assert_eq!(vocab.calibration_value("xyz"), None);
+
+    assert!(Vocabulary::new([(String::new(), 1)]).is_err());
+    assert!(Vocabulary::new([("one".to_owned(), 1), ("one".to_owned(), 2)]).is_err());
+  }
+
+  #[test]
+  fn test_large_values() {
+    assert_eq!(concat(0, 0), Some(0));
+    assert_eq!(concat(42, 1_000_000_000), None);
+    assert_eq!(concat(0, u32::MAX), Some(u32::MAX));
+    assert_eq!(concat(42949, 67295), Some(u32::MAX));
+    assert_eq!(concat(42949, 67296), None);
+    let vocab = Vocabulary::with_digits([("big".to_owned(), 42949)]).unwrap();
+    assert_eq!(vocab.calibration_value("big1big"), Some(4294942949));
+    let err = Vocabulary::with_digits([("huge".to_owned(), 1_000_000_000)]).err().unwrap();
+    assert_eq!(err.to_string(), "vocabulary value 1000000000 is too large to make calibration values of 32 bits");
+
+    let mut params = Params::default();
+    assert!(params.set("words", "big=100000").is_err());
+    params.set("words", "big=42949").unwrap();
+    let err = Solver::part2(&"big\nbig\n".to_owned(), &params).unwrap_err();
+    assert_eq!(err.to_string(), "day 1: calibration values add up past 32 bits");
+  }
+
+  #[test]
+  fn test_words_param() {
+    let mut params = Params::default();
+    params.set("words", "un=1,deux=2,trois=3").unwrap();
+    assert_eq!(Solver::part2(&"undeux\n4trois\n".to_owned(), &params), Ok(Answer(12 + 43)));
+    assert!(params.set("words", "un:1").is_err());
+    assert!(params.set("words", "un=x").is_err());
+  }
+
+  #[test]
+  fn test_report() {
+    let text = "eightwo\nxyz\na1b\nëone4\n";
+    let reports = report(text, &Vocabulary::english());
+    assert_eq!(reports.iter().map(|report| report.value).collect::<Vec<_>>(), [Some(82), None, Some(11), Some(14)]);
+    let (first, last) = reports[0].ends.unwrap();
+    assert_eq!((first.span(), first.kind, last.span(), last.kind), (0..5, TokenKind::Word, 4..7, TokenKind::Word));
+    assert_eq!(reports[2].ends.unwrap().0.kind, TokenKind::Digit);
+
+    let rendered: Vec<_> = reports.iter().map(LineReport::to_string).collect();
+    assert_eq!(rendered, [
+      "eightwo => 82\n^^^^^ word 8 (0..5)\n    ^^^ word 2 (4..7)",
+      "xyz => no digits",
+      "a1b => 11\n ^ digit 1 (1..2)",
+      "ëone4 => 14\n ^^^ word 1 (2..5)\n    ^ digit 4 (5..6)",
+    ]);
   }
 }
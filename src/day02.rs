@@ -1,30 +1,137 @@
-use crate::util::usize;
+use std::{collections::BTreeMap, io::BufRead};
 
-use enum_map::{EnumMap, Enum};
-use nom::{IResult, character::complete::char, bytes::complete::tag, sequence::{separated_pair, delimited, terminated}, multi::separated_list1, branch::alt, Parser, combinator::eof};
+use crate::{util::usize, solution::{self, read_input, joined_lines, parse_param_pairs, unknown_param, Solution, Answer}, error::{Result, parse_lines}};
 
-pub fn part1(lines: impl Iterator<Item=String>) -> usize {
-  lines
-    .map(|line| parse(&line).unwrap().1)
-    .filter(|g| g.rounds.iter().all(possible))
+use nom::{IResult, character::complete::{char, alpha1}, bytes::complete::tag, sequence::{separated_pair, delimited, terminated}, multi::separated_list1, combinator::eof};
+
+pub fn part1(input: impl BufRead) -> Result<usize> { Ok(sum_feasible(&parse_games(read_input(input)?.lines())?, &Params::default().bag)) }
+pub fn part2(input: impl BufRead) -> Result<usize> { Ok(sum_powers(&parse_games(read_input(input)?.lines())?, &Params::default().bag)) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part2(joined_lines(lines)) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 2;
+  type Input = Vec<Game>;
+  type Params = Params;
+
+  fn parse(input: &str) -> Result<Self::Input> { parse_games(input.lines()) }
+  fn part1(games: &Self::Input, params: &Params) -> Result<Answer> { Ok(sum_feasible(games, &params.bag).into()) }
+  fn part2(games: &Self::Input, params: &Params) -> Result<Answer> { Ok(sum_powers(games, &params.bag).into()) }
+}
+
+// The bag's contents, given like "red=12,green=13,blue=14". Part 2 multiplies the minimum counts of the bag's colours
+pub struct Params {
+  pub bag: Bag,
+}
+
+impl Default for Params {
+  fn default() -> Self {
+    Self { bag: Cubes::from_iter([("red", 12), ("green", 13), ("blue", 14)]) }
+  }
+}
+
+impl solution::Params for Params {
+  const NAMES: &'static [&'static str] = &["bag"];
+
+  fn set(&mut self, name: &str, value: &str) -> Result<()> {
+    match name {
+      "bag" => self.bag = parse_param_pairs::<usize>(name, value)?.into_iter().collect(),
+      _ => return unknown_param(name),
+    }
+    Ok(())
+  }
+}
+
+fn parse_games(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Vec<Game>> {
+  parse_lines(Solver::DAY, lines, parse)
+}
+
+fn sum_feasible(games: &[Game], bag: &Bag) -> usize {
+  feasible_games(games, bag)
     .map(|g| g.id)
     .sum()
 }
-pub fn part2(lines: impl Iterator<Item=String>) -> usize {
-  lines
-    .map(|line| parse(&line).unwrap().1)
-    .map(|g| power(&g))
+
+fn sum_powers(games: &[Game], bag: &Bag) -> usize {
+  min_bags(games)
+    .map(|min_bag| bag.colours().map(|colour| min_bag.count(colour)).product::<usize>())
     .sum()
 }
 
-#[derive(Enum, Clone, Copy)]
-enum Color { Red, Green, Blue }
+pub fn feasible_games<'a>(games: &'a [Game], bag: &'a Bag) -> impl Iterator<Item=&'a Game> {
+  games.iter().filter(|g| g.violation(bag).is_none())
+}
+
+pub fn min_bags(games: &[Game]) -> impl Iterator<Item=Bag> + '_ {
+  games.iter().map(Game::min_bag)
+}
+
+// Counts of cubes by colour. Colours not listed count as zero
+#[derive(Clone, Default, PartialEq, Eq, Debug)]
+pub struct Cubes {
+  counts: BTreeMap<String, usize>,
+}
+
+pub type Round = Cubes;
+pub type Bag = Cubes;
+
+impl Cubes {
+  pub fn count(&self, colour: &str) -> usize {
+    self.counts.get(colour).copied().unwrap_or_default()
+  }
+
+  pub fn colours(&self) -> impl Iterator<Item=&str> {
+    self.counts.keys().map(String::as_str)
+  }
+}
+
+impl<S: Into<String>> FromIterator<(S, usize)> for Cubes {
+  // Repeated colours add up
+  fn from_iter<I: IntoIterator<Item=(S, usize)>>(iter: I) -> Self {
+    let mut counts = BTreeMap::new();
+    for (colour, n) in iter {
+      *counts.entry(colour.into()).or_default() += n;
+    }
+    Self { counts }
+  }
+}
+
+pub struct Game {
+  pub id: usize,
+  pub rounds: Vec<Round>,
+}
 
-type Round = EnumMap<Color, usize>;
+// The first colour of a round that the bag cannot have supplied
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Violation {
+  pub round: usize,
+  pub colour: String,
+  pub drawn: usize,
+  pub available: usize,
+}
 
-struct Game {
-  id: usize,
-  rounds: Vec<Round>,
+impl Game {
+  pub fn violation(&self, bag: &Bag) -> Option<Violation> {
+    self.rounds.iter()
+      .enumerate()
+      .find_map(|(i, round)| round.counts.iter()
+        .find(|&(colour, &drawn)| drawn > bag.count(colour))
+        .map(|(colour, &drawn)| Violation { round: i, colour: colour.clone(), drawn, available: bag.count(colour) }))
+  }
+
+  // The smallest bag that could have supplied every round
+  pub fn min_bag(&self) -> Bag {
+    let mut mins = Bag::default();
+    for round in &self.rounds {
+      for (colour, &n) in &round.counts {
+        let min = mins.counts.entry(colour.clone()).or_default();
+        *min = Ord::max(n, *min);
+      }
+    }
+    mins
+  }
 }
 
 fn parse(input: &str) -> IResult<&str, Game> {
@@ -45,59 +152,63 @@ fn parse(input: &str) -> IResult<&str, Game> {
     .collect();
   Ok((input, Game { id, rounds }))
 }
-fn parse_entry(input: &str) -> IResult<&str, (Color, usize)> {
-  let parse_one_color = |text: &'static str, color: Color| tag(text).map(move |_| color);
-  let parse_color = alt((
-    parse_one_color("red"  , Color::Red  ),
-    parse_one_color("green", Color::Green),
-    parse_one_color("blue" , Color::Blue ),
-  ));
+fn parse_entry(input: &str) -> IResult<&str, (&str, usize)> {
   let (input, (v, k)) = separated_pair(
     usize,
     char(' '),
-    parse_color,
+    alpha1,
   )(input)?;
   Ok((input, (k, v)))
 }
 
-fn possible(round: &Round) -> bool {
-     round[Color::Red]   <= 12
-  && round[Color::Green] <= 13
-  && round[Color::Blue]  <= 14
-}
-
-fn power(game: &Game) -> usize {
-  let mut mins: Round = Default::default();
-  for round in &game.rounds {
-    for (k, &v) in round {
-      mins[k] = Ord::max(v, mins[k]);
-    }
-  }
-  mins.into_values().product()
-}
-
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::{solution::Params as _, test_util::{sample_file, sample_input}};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("02a")), 8);
+    assert_eq!(part1(sample_input("02a")), Ok(8));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("02")), 3099);
+    assert_eq!(part1(sample_input("02")), Ok(3099));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("02a")), 2286);
+    assert_eq!(part2(sample_input("02a")), Ok(2286));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("02")), 72970);
+    assert_eq!(part2(sample_input("02")), Ok(72970));
+  }
+
+  #[test]
+  fn test_queries() {
+    let games = parse_games(sample_file("02a").lines()).unwrap();
+    let bag = Params::default().bag;
+    assert!(feasible_games(&games, &bag).map(|g| g.id).eq([1, 2, 5]));
+    assert_eq!(games[2].violation(&bag), Some(Violation { round: 0, colour: "red".to_owned(), drawn: 20, available: 12 }));
+    assert_eq!(games[0].min_bag(), Cubes::from_iter([("red", 4), ("green", 2), ("blue", 6)]));
+
+    let games = parse_games(["Game 7: 2 purple, 1 red, 3 purple; 4 teal"].into_iter()).unwrap();
+    assert_eq!(games[0].min_bag(), Cubes::from_iter([("purple", 5), ("red", 1), ("teal", 4)]));
+    let bag = Cubes::from_iter([("purple", 5), ("teal", 3), ("red", 1)]);
+    assert_eq!(games[0].violation(&bag).map(|v| v.round), Some(1));
+  }
+
+  #[test]
+  fn test_bag_param() {
+    let games = parse_games(sample_file("02a").lines()).unwrap();
+    let mut params = Params::default();
+    params.set("bag", "red=20,green=13,blue=15").unwrap();
+    assert_eq!(Solver::part1(&games, &params), Ok(Answer(1 + 2 + 3 + 4 + 5)));
+    // Only red and green count towards the power
+    params.set("bag", "red=20,green=20").unwrap();
+    assert_eq!(Solver::part2(&games, &params), Ok(Answer(8 + 3 + 260 + 42 + 18)));
+    assert!(params.set("bag", "red:20").is_err());
   }
 }
@@ -1,111 +1,228 @@
-use std::collections::HashMap;
+use std::{collections::BTreeMap, io::BufRead};
 
 use bit_set::BitSet;
 
-pub fn part1(lines: impl Iterator<Item=String>) -> usize {
-  let schem = parse(lines);
+use crate::{util::{Coord, Grid}, solution::{self, read_input, joined_lines, parse_param, unknown_param, Solution, Answer}, error::Result};
 
-  let mut inc_nums = BitSet::with_capacity(schem.nums.len());
-  for (&(y, x), _) in &schem.syms {
-    for dy in -1..=1 {
-      for dx in -1..=1 {
-        if let Some(&i) = schem.num_find.get(&(y+dy, x+dx)) {
-          inc_nums.insert(i);
-        }
-      }
+pub fn part1(input: impl BufRead) -> Result<usize> { Ok(sum_part_numbers(&parse(read_input(input)?.lines())?)) }
+pub fn part2(input: impl BufRead) -> Result<usize> { Ok(sum_gear_ratios(&parse(read_input(input)?.lines())?, &Params::default())) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part2(joined_lines(lines)) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 3;
+  type Input = Schematic;
+  type Params = Params;
+
+  fn parse(input: &str) -> Result<Self::Input> { parse(input.lines()) }
+  fn part1(schem: &Self::Input, _: &Params) -> Result<Answer> { Ok(sum_part_numbers(schem).into()) }
+  fn part2(schem: &Self::Input, params: &Params) -> Result<Answer> { Ok(sum_gear_ratios(schem, params).into()) }
+}
+
+// Which symbol makes a gear, and how many numbers it must touch
+pub struct Params {
+  pub gear: char,
+  pub gear_numbers: usize,
+}
+
+impl Default for Params {
+  fn default() -> Self { Self { gear: '*', gear_numbers: 2 } }
+}
+
+impl solution::Params for Params {
+  const NAMES: &'static [&'static str] = &["gear", "gear_numbers"];
+
+  fn set(&mut self, name: &str, value: &str) -> Result<()> {
+    match name {
+      "gear" => self.gear = parse_param(name, value)?,
+      "gear_numbers" => self.gear_numbers = parse_param(name, value)?,
+      _ => return unknown_param(name),
     }
+    Ok(())
   }
+}
 
-  inc_nums.into_iter()
-    .map(|i| schem.nums[i].1)
+fn sum_part_numbers(schem: &Schematic) -> usize {
+  schem.parts(|_| true)
+    .into_iter()
+    .map(|num| num.value)
     .sum()
 }
 
-pub fn part2(lines: impl Iterator<Item=String>) -> usize {
-  let schem = parse(lines);
-
-  schem.syms.iter()
-    .filter(|(_, &is_gear)| is_gear)
-    .filter_map(|(&(y, x), _)| -> Option<usize> {
-      let mut inc_nums = BitSet::with_capacity(schem.nums.len());
-      for dy in -1..=1 {
-        for dx in -1..=1 {
-          if let Some(&i) = schem.num_find.get(&(y+dy, x+dx)) {
-            inc_nums.insert(i);
-            if inc_nums.len() > 2 { return None; }
-          }
-        }
-      }
-      if inc_nums.len() == 2 {
-        Some(inc_nums.into_iter().map(|i| schem.nums[i].1).product())
-      } else { None }
-    })
+fn sum_gear_ratios(schem: &Schematic, &Params { gear, gear_numbers }: &Params) -> usize {
+  schem.gears(|c| c == gear, gear_numbers)
+    .iter()
+    .map(Gear::ratio)
     .sum()
 }
 
-type Coord = (isize, isize);
+// A number and where its first digit is
+#[derive(PartialEq, Eq, Debug)]
+pub struct Number {
+  pub pos: Coord,
+  pub len: usize,
+  pub value: usize,
+}
+
+// A symbol touching exactly the given numbers
+#[derive(PartialEq, Eq, Debug)]
+pub struct Gear<'a> {
+  pub pos: Coord,
+  pub symbol: char,
+  pub numbers: Vec<&'a Number>,
+}
 
-struct Number(Coord, usize);
+impl Gear<'_> {
+  pub fn ratio(&self) -> usize {
+    self.numbers.iter().map(|num| num.value).product()
+  }
+}
 
-#[derive(Default)]
-struct Schematic {
+pub struct Schematic {
   nums: Vec<Number>,
-  num_find: HashMap<Coord, usize>,
-  syms: HashMap<Coord, bool>,
+  // The number covering each digit's cell, as an index into nums
+  num_find: Grid<Option<usize>>,
+  syms: BTreeMap<Coord, char>,
 }
 
-fn parse(lines: impl Iterator<Item=String>) -> Schematic {
-  let mut schem = Schematic::default();
+impl Schematic {
+  // In reading order
+  pub fn numbers(&self) -> &[Number] {
+    &self.nums
+  }
 
-  for (y, line) in lines.enumerate() {
-    let y = y.try_into().unwrap();
+  // In reading order
+  pub fn symbols(&self) -> impl Iterator<Item=(Coord, char)> + '_ {
+    self.syms.iter().map(|(&pos, &c)| (pos, c))
+  }
 
-    let mut num = None;
-    for (x, char) in line.chars().enumerate() {
-      let x = x.try_into().unwrap();
+  pub fn number_at(&self, pos: Coord) -> Option<&Number> {
+    self.num_find.get(pos).copied().flatten().map(|i| &self.nums[i])
+  }
 
+  // The numbers with a digit in any of the eight cells around `pos`, each once and in reading order
+  pub fn adjacent_numbers(&self, pos: Coord) -> Vec<&Number> {
+    self.adjacent_idxs(pos).into_iter().map(|i| &self.nums[i]).collect()
+  }
+
+  // The numbers next to any symbol of the class, in reading order
+  pub fn parts(&self, class: impl Fn(char) -> bool) -> Vec<&Number> {
+    let mut inc_nums = BitSet::with_capacity(self.nums.len());
+    for (&pos, _) in self.syms.iter().filter(|&(_, &c)| class(c)) {
+      inc_nums.union_with(&self.adjacent_idxs(pos));
+    }
+    inc_nums.into_iter().map(|i| &self.nums[i]).collect()
+  }
+
+  // The symbols of the class next to exactly `count` numbers, in reading order
+  pub fn gears(&self, class: impl Fn(char) -> bool, count: usize) -> Vec<Gear<'_>> {
+    self.syms.iter()
+      .filter(|&(_, &c)| class(c))
+      .filter_map(|(&pos, &symbol)| {
+        let numbers = self.adjacent_numbers(pos);
+        (numbers.len() == count).then_some(Gear { pos, symbol, numbers })
+      })
+      .collect()
+  }
+
+  fn adjacent_idxs(&self, pos: Coord) -> BitSet {
+    let mut idxs = BitSet::with_capacity(self.nums.len());
+    for nbr in self.num_find.neighbours8(pos).chain([pos]) {
+      if let Some(&Some(i)) = self.num_find.get(nbr) {
+        idxs.insert(i);
+      }
+    }
+    idxs
+  }
+}
+
+fn parse(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Schematic> {
+  let map = Grid::parse(Solver::DAY, lines, Some)?;
+  let mut schem = Schematic { nums: vec![], num_find: Grid::new(map.size(), None), syms: BTreeMap::new() };
+
+  for (y, row) in map.rows().enumerate() {
+    let mut num = None;
+    for (x, &char) in row.iter().enumerate() {
       if let Some(digit) = char.to_digit(10) {
-        schem.num_find.insert((y, x), schem.nums.len());
-        let digit = digit.try_into().unwrap();
+        schem.num_find[(y, x)] = Some(schem.nums.len());
+        let digit: usize = digit.try_into().unwrap();
         num = Some(match num {
-          None => Number((y, x), digit),
-          Some(Number(coord, old_digits)) => Number(coord, old_digits*10 + digit),
+          None => Number { pos: (y, x), len: 1, value: digit },
+          Some(Number { pos, len, value }) => Number { pos, len: len + 1, value: value*10 + digit },
         });
       } else {
         if let Some(n) = num.take() { schem.nums.push(n); }
         if char != '.' {
-          schem.syms.insert((y, x), char == '*');
+          schem.syms.insert((y, x), char);
         }
       }
     }
-    schem.nums.extend(num.into_iter());
+    schem.nums.extend(num);
   }
 
-  schem
+  Ok(schem)
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::{solution::Params as _, test_util::{sample_file, sample_input}};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("03a")), 4361);
+    assert_eq!(part1(sample_input("03a")), Ok(4361));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("03")), 514969);
+    assert_eq!(part1(sample_input("03")), Ok(514969));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("03a")), 467835);
+    assert_eq!(part2(sample_input("03a")), Ok(467835));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("03")), 78915902);
+    assert_eq!(part2(sample_input("03")), Ok(78915902));
+  }
+
+  #[test]
+  fn test_queries() {
+    let schem = parse(sample_file("03a").lines()).unwrap();
+    assert_eq!(schem.numbers().len(), 10);
+    assert_eq!(schem.number_at((0, 2)), Some(&Number { pos: (0, 0), len: 3, value: 467 }));
+    assert_eq!(schem.number_at((0, 3)), None);
+    assert!(schem.symbols().map(|(_, c)| c).eq("*#*+$*".chars()));
+
+    let part_values = |class: fn(char) -> bool| schem.parts(class).iter().map(|num| num.value).collect::<Vec<_>>();
+    assert_eq!(part_values(|_| true), [467, 35, 633, 617, 592, 755, 664, 598]);
+    assert_eq!(part_values(|c| c == '#' || c == '$'), [633, 664]);
+
+    let gears = schem.gears(|c| c == '*', 2);
+    assert_eq!(gears.iter().map(|gear| (gear.pos, gear.ratio())).collect::<Vec<_>>(), [((1, 3), 16345), ((8, 5), 451490)]);
+    let lone = schem.gears(|c| c == '*', 1);
+    assert_eq!(lone.iter().map(|gear| (gear.pos, gear.symbol, gear.ratio())).collect::<Vec<_>>(), [((4, 3), '*', 617)]);
+  }
+
+  #[test]
+  fn test_gear_params() {
+    let schem = parse("1.2\n.*.\n3..\n".lines()).unwrap();
+    let mut params = Params::default();
+    assert_eq!(Solver::part2(&schem, &params), Ok(Answer(0)));
+    params.set("gear_numbers", "3").unwrap();
+    assert_eq!(Solver::part2(&schem, &params), Ok(Answer(6)));
+    params.set("gear", "#").unwrap();
+    assert_eq!(Solver::part2(&schem, &params), Ok(Answer(0)));
+    assert!(params.set("gear", "##").is_err());
+  }
+
+  #[test]
+  fn test_parse_error() {
+    let err = part1("1.\n...\n".as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 3, line 2, column 1: row length differs from the first row at \"...\"");
   }
 }
@@ -1,38 +1,106 @@
-use crate::util::usize;
+use crate::{util::usize, solution::{self, read_input, joined_lines, unknown_param, Solution, Answer}, error::{Error, Result, parse_all}};
 
-use std::collections::{HashSet, VecDeque};
+use std::{collections::HashSet, io::BufRead, ops::AddAssign};
 
 use nom::{IResult, character::complete::char, bytes::complete::tag, multi::{many1, many0}, sequence::{pair, delimited, terminated, preceded}, combinator::eof};
+use num::{BigUint, One};
 
-pub fn part1(lines: impl Iterator<Item=String>) -> usize {
+pub fn part1(input: impl BufRead) -> Result<usize> { Ok(sum_scores(&parse(read_input(input)?.lines())?, doubling)) }
+pub fn part2(input: impl BufRead) -> Result<Answer> { total_copies(&parse(read_input(input)?.lines())?) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<Answer> { part2(joined_lines(lines)) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 4;
+  type Input = Vec<Card>;
+  type Params = Params;
+
+  fn parse(input: &str) -> Result<Self::Input> { parse(input.lines()) }
+  fn part1(cards: &Self::Input, params: &Params) -> Result<Answer> { Ok(sum_scores(cards, params.scoring).into()) }
+  fn part2(cards: &Self::Input, _: &Params) -> Result<Answer> { total_copies(cards) }
+}
+
+// Part 1's scoring rule, by name
+pub struct Params {
+  pub scoring: fn(usize) -> usize,
+}
+
+impl Default for Params {
+  fn default() -> Self { Self { scoring: doubling } }
+}
+
+impl solution::Params for Params {
+  const NAMES: &'static [&'static str] = &["scoring"];
+
+  fn set(&mut self, name: &str, value: &str) -> Result<()> {
+    match name {
+      "scoring" => self.scoring = match value {
+        "doubling" => doubling,
+        "linear" => linear,
+        "square" => square,
+        _ => return Err(Error::Usage(format!("unknown scoring rule {:?}, expected doubling, linear or square", value))),
+      },
+      _ => return unknown_param(name),
+    }
+    Ok(())
+  }
+}
+
+fn parse(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Vec<Card>> {
   lines
-    .map(|line| Card::parse(&line).unwrap().1)
-    .map(|c| c.score())
+    .enumerate()
+    .map(|(i, line)| {
+      let card = parse_all(Solver::DAY, i, line.as_ref(), Card::parse)?;
+      if card.id != i + 1 {
+        return Err(Error::parse(Solver::DAY, i, line.as_ref(), 0, format!("expected card {}", i + 1)));
+      }
+      Ok(card)
+    })
+    .collect()
+}
+
+// Scores each card from its number of matches
+pub fn sum_scores(cards: &[Card], scoring: impl Fn(usize) -> usize) -> usize {
+  cards.iter()
+    .map(|c| scoring(c.matches()))
     .sum()
 }
 
-pub fn part2(lines: impl Iterator<Item=String>) -> usize {
-  let cards = lines
-    .map(|line| Card::parse(&line).unwrap().1);
-
-  let mut counts = VecDeque::new();
-  let mut sum = 0;
-  for (i, card) in cards.enumerate() {
-    assert!(card.id == i + 1);
-    let count = counts.pop_front().unwrap_or(1);
-    sum += count;
-    let matches = card.matches();
-    while counts.len() < matches {
-      counts.push_back(1);
-    }
-    for j in 0..matches {
-      counts[j] += count;
+pub fn doubling(matches: usize) -> usize {
+  match matches {
+    0 => 0,
+    n => 1 << (n-1),
+  }
+}
+pub fn linear(matches: usize) -> usize {
+  matches
+}
+pub fn square(matches: usize) -> usize {
+  matches * matches
+}
+
+// How many of each card we end up with, counting the original. Counts grow exponentially with long runs of winning
+// cards, so N can be BigUint
+pub fn copy_counts<N>(cards: &[Card]) -> Vec<N>
+where N: Clone + One + for<'a> AddAssign<&'a N> {
+  let mut counts = vec![N::one(); cards.len()];
+  for (i, card) in cards.iter().enumerate() {
+    let (won_by, rest) = counts.split_at_mut(i + 1);
+    for next_count in rest.iter_mut().take(card.matches()) {
+      *next_count += &won_by[i];
     }
   }
-  sum
+  counts
 }
 
-struct Card {
+// Totalled as a BigUint, so that a pile too large for an Answer is an Overflow error rather than a wrapped sum
+fn total_copies(cards: &[Card]) -> Result<Answer> {
+  copy_counts::<BigUint>(cards).into_iter().sum::<BigUint>().try_into()
+}
+
+pub struct Card {
   id: usize,
   winners: HashSet<usize>,
   nums: Vec<usize>,
@@ -59,42 +127,65 @@ impl Card {
     Ok((input, Self { id, winners, nums }))
   }
 
-  fn matches(&self) -> usize {
+  pub fn matches(&self) -> usize {
     self.nums.iter()
       .filter(|n| self.winners.contains(n))
       .count()
   }
-
-  fn score(&self) -> usize {
-    match self.matches() {
-      0 => 0,
-      n => 1 << n-1,
-    }
-  }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::{generate, solution::Params as _, test_util::{sample_file, sample_input}};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("04a")), 13);
+    assert_eq!(part1(sample_input("04a")), Ok(13));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("04")), 22897);
+    assert_eq!(part1(sample_input("04")), Ok(22897));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("04a")), 30);
+    assert_eq!(part2(sample_input("04a")), Ok(Answer(30)));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("04")), 5095824);
+    assert_eq!(part2(sample_input("04")), Ok(Answer(5095824)));
+  }
+
+  #[test]
+  fn test_copy_counts() {
+    let cards = parse(sample_file("04a").lines()).unwrap();
+    assert_eq!(copy_counts::<usize>(&cards), [1, 2, 4, 8, 14, 1]);
+    assert_eq!(copy_counts::<BigUint>(&cards), [1u32, 2, 4, 8, 14, 1].map(BigUint::from));
+
+    let cards = parse(generate::day04(30, 0).lines()).unwrap();
+    let small: Vec<_> = copy_counts::<usize>(&cards).into_iter().map(BigUint::from).collect();
+    assert_eq!(copy_counts::<BigUint>(&cards), small);
+
+    let cards = parse(generate::day04(500, 0).lines()).unwrap();
+    let total: BigUint = copy_counts::<BigUint>(&cards).into_iter().sum();
+    assert!(total.bits() > 128);
+    assert_eq!(Solver::part2(&cards, &Params::default()), Err(Error::Overflow(total.to_string())));
+    assert_eq!(part2(generate::day04(500, 0).as_bytes()), Err(Error::Overflow(total.to_string())));
+  }
+
+  #[test]
+  fn test_scoring() {
+    let cards = parse(sample_file("04a").lines()).unwrap();
+    let mut params = Params::default();
+    assert_eq!(Solver::part1(&cards, &params), Ok(Answer(13)));
+    params.set("scoring", "linear").unwrap();
+    assert_eq!(Solver::part1(&cards, &params), Ok(Answer(4 + 2 + 2 + 1)));
+    params.set("scoring", "square").unwrap();
+    assert_eq!(Solver::part1(&cards, &params), Ok(Answer(16 + 4 + 4 + 1)));
+    assert_eq!(sum_scores(&cards, |matches| matches.min(1)), 4);
+    assert!(params.set("scoring", "tripling").is_err());
   }
 }
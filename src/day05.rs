@@ -1,58 +1,182 @@
-use std::{collections::BTreeMap, ops::Bound};
+use std::{collections::{BTreeMap, HashMap, HashSet, VecDeque, btree_map}, io::BufRead, iter::Peekable};
 
-use crate::util::usize;
+use crate::{util::{usize, range_from_floor, TryInsert}, solution::{self, read_input, parse_param, unknown_param, Solution, Answer}, error::{Error, Result, parse_all}};
 
-use nom::{IResult, character::complete::{char, line_ending}, bytes::complete::tag, multi::{many0, many1}, sequence::{delimited, preceded, terminated, tuple, separated_pair}, combinator::eof, Parser};
+use nom::{IResult, Offset, character::complete::{alpha1, char, line_ending}, bytes::complete::tag, multi::{many0, many1}, sequence::{delimited, preceded, terminated, tuple}, combinator::{cut, eof}};
 
-pub fn part1(file: String) -> usize { process(file, parse_single_seeds) }
-pub fn part2(file: String) -> usize { process(file, parse_seed_ranges) }
+pub fn part1(input: impl BufRead) -> Result<usize> { lowest_location(&parse_almanac(&read_input(input)?)?, &Params::default(), single_seeds) }
+pub fn part2(input: impl BufRead) -> Result<usize> { lowest_location(&parse_almanac(&read_input(input)?)?, &Params::default(), seed_ranges) }
+pub fn part1_file(file: String) -> Result<usize> { part1(file.as_bytes()) }
+pub fn part2_file(file: String) -> Result<usize> { part2(file.as_bytes()) }
 
-fn process(file: String, parse_seeds: impl FnMut(&str) -> IResult<&str, Vec<Range>>) -> usize {
-  let (_, (seed_ranges, maps)) = parse(&file, parse_seeds).unwrap();
+pub struct Solver;
 
-  let mut ranges: Box<dyn Iterator<Item=Range>> = Box::new(seed_ranges.into_iter());
-  for map in &maps {
-    ranges = Box::new(ranges.flat_map(|r| map.translate(r)));
+impl Solution for Solver {
+  const DAY: u8 = 5;
+  type Input = Almanac;
+  type Params = Params;
+
+  fn parse(input: &str) -> Result<Self::Input> { parse_almanac(input) }
+  fn part1(almanac: &Self::Input, params: &Params) -> Result<Answer> { lowest_location(almanac, params, single_seeds).map(Answer::from) }
+  fn part2(almanac: &Self::Input, params: &Params) -> Result<Answer> { lowest_location(almanac, params, seed_ranges).map(Answer::from) }
+}
+
+// The categories the seeds line is in and the answer is wanted in
+pub struct Params {
+  pub from: String,
+  pub to: String,
+}
+
+impl Default for Params {
+  fn default() -> Self { Self { from: "seed".to_owned(), to: "location".to_owned() } }
+}
+
+impl solution::Params for Params {
+  const NAMES: &'static [&'static str] = &["from", "to"];
+
+  fn set(&mut self, name: &str, value: &str) -> Result<()> {
+    match name {
+      "from" => self.from = parse_param(name, value)?,
+      "to" => self.to = parse_param(name, value)?,
+      _ => return unknown_param(name),
+    }
+    Ok(())
+  }
+}
+
+#[derive(Debug)]
+pub struct Almanac {
+  seeds: Vec<usize>,
+  // The maps out of each category, by the category they lead to. There are no cycles
+  maps: HashMap<String, BTreeMap<String, Map>>,
+}
+
+impl Almanac {
+  // The maps along the shortest chain of categories from one to the other
+  pub fn chain(&self, from: &str, to: &str) -> Result<Vec<&Map>> {
+    let mut prev: HashMap<&str, (&str, &Map)> = HashMap::new();
+    let mut to_visit = VecDeque::from([from]);
+    while let Some(cat) = to_visit.pop_front() {
+      if cat == to {
+        let mut chain = vec![];
+        let mut cat = cat;
+        while let Some(&(prev_cat, map)) = prev.get(cat) {
+          chain.push(map);
+          cat = prev_cat;
+        }
+        chain.reverse();
+        return Ok(chain);
+      }
+      for (next, map) in self.maps.get(cat).into_iter().flatten() {
+        if prev.insert_vacant(next, (cat, map)).is_ok() {
+          to_visit.push_back(next);
+        }
+      }
+    }
+    Err(Error::Usage(format!("no chain of maps from {} to {}", from, to)))
+  }
+
+  // The maps along the chain collapsed into one
+  pub fn composed(&self, from: &str, to: &str) -> Result<Map> {
+    Ok(self.chain(from, to)?.into_iter().fold(Map::default(), |composed, map| composed.then(map)))
+  }
+
+  pub fn translate(&self, from: &str, to: &str, n: usize) -> Result<usize> {
+    Ok(self.chain(from, to)?.into_iter().fold(n, |n, map| map.get(n)))
   }
-  ranges.map(|t| t.0).min().unwrap()
 }
 
-fn parse(input: &str, parse_seeds: impl FnMut(&str) -> IResult<&str, Vec<Range>>) -> IResult<&str, (Vec<Range>, Vec<Map>)> {
-  let (mut input, seeds) = delimited(
+fn lowest_location(almanac: &Almanac, Params { from, to }: &Params, seed_ranges: impl Fn(&[usize]) -> Result<Vec<Range>>) -> Result<usize> {
+  let map = almanac.composed(from, to)?;
+  seed_ranges(&almanac.seeds)?.into_iter()
+    .flat_map(|r| map.translate(r))
+    .map(|t| t.0)
+    .min()
+    .ok_or_else(|| Error::input(Solver::DAY, "no seeds"))
+}
+
+fn parse_almanac(input: &str) -> Result<Almanac> {
+  let (seeds, sections) = parse_all(Solver::DAY, 0, input, parse)?;
+  // Section names are slices of the input, so each header's position is where its first name starts
+  let header_error = |from: &str, reason: String| Error::parse(Solver::DAY, 0, input, input.offset(from), reason);
+
+  let mut maps: HashMap<String, BTreeMap<String, Map>> = HashMap::new();
+  let mut headers = HashMap::new();
+  for (from, to, map) in sections {
+    if maps.entry(from.to_owned()).or_default().insert(to.to_owned(), map).is_some() {
+      return Err(header_error(from, format!("second map from {} to {}", from, to)));
+    }
+    headers.insert((from, to), from);
+  }
+
+  let mut done = HashSet::new();
+  let mut cats: Vec<_> = maps.keys().collect();
+  cats.sort();
+  for cat in cats {
+    if let Some(cycle) = find_cycle(&maps, cat, &mut vec![], &mut done) {
+      let header = headers[&(cycle[cycle.len() - 2], cycle[cycle.len() - 1])];
+      return Err(header_error(header, format!("maps form a cycle: {}", cycle.join(" -> "))));
+    }
+  }
+  Ok(Almanac { seeds, maps })
+}
+
+// A cycle reachable from `cat`, as the categories around it with the first repeated at the end
+fn find_cycle<'a>(maps: &'a HashMap<String, BTreeMap<String, Map>>, cat: &'a str, path: &mut Vec<&'a str>, done: &mut HashSet<&'a str>) -> Option<Vec<&'a str>> {
+  if let Some(i) = path.iter().position(|&on_path| on_path == cat) {
+    let mut cycle = path[i..].to_vec();
+    cycle.push(cat);
+    return Some(cycle);
+  }
+  // Anything seen before and not on the path has been fully explored
+  if !done.insert(cat) { return None; }
+  path.push(cat);
+  for next in maps.get(cat).into_iter().flat_map(BTreeMap::keys) {
+    if let Some(cycle) = find_cycle(maps, next, path, done) {
+      return Some(cycle);
+    }
+  }
+  path.pop();
+  None
+}
+
+type Section<'a> = (&'a str, &'a str, Map);
+
+fn parse(input: &str) -> IResult<&str, (Vec<usize>, Vec<Section<'_>>)> {
+  let (input, seeds) = delimited(
     tag("seeds:"),
-    parse_seeds,
+    many1(preceded(char(' '), usize)),
     line_ending,
   )(input)?;
-  let mut maps = vec![];
-  for elems in ["seed", "soil", "fertilizer", "water", "light", "temperature", "humidity", "location"].windows(2) {
-    let (input1, map) = preceded(
-      tuple((
-        line_ending,
-        tag(format!("{}-to-{} map:", elems[0], elems[1]).as_str()),
-        line_ending,
-      )),
+  // Past a blank line there must be another section
+  let (input, sections) = many1(preceded(
+    line_ending,
+    cut(tuple((
+      terminated(alpha1, tag("-to-")),
+      terminated(alpha1, tuple((tag(" map:"), line_ending))),
       Map::parse,
-    )(input)?;
-    input = input1;
-    maps.push(map);
-  }
+    ))),
+  ))(input)?;
   let (input, _) = eof(input)?;
-  Ok((input, (seeds, maps)))
+  Ok((input, (seeds, sections)))
 }
 
-fn parse_single_seeds(input: &str) -> IResult<&str, Vec<Range>> {
-  many1(preceded(char(' '), usize))
-    .map(|seeds| seeds.into_iter().map(|i| (i, 1)).collect())
-    .parse(input)
+fn single_seeds(seeds: &[usize]) -> Result<Vec<Range>> {
+  Ok(seeds.iter().map(|&i| (i, 1)).collect())
 }
-fn parse_seed_ranges(input: &str) -> IResult<&str, Vec<Range>> {
-  many1(preceded(char(' '), separated_pair(usize, char(' '), usize)))(input)
+fn seed_ranges(seeds: &[usize]) -> Result<Vec<Range>> {
+  if !seeds.len().is_multiple_of(2) {
+    return Err(Error::input(Solver::DAY, "seed ranges need an even number of values"));
+  }
+  Ok(seeds.chunks(2).map(|pair| (pair[0], pair[1])).collect())
 }
 
 type Range = (usize, usize);
 
-#[derive(Default)]
-struct Map {
+// A piecewise-linear map, as source ranges shifted to destinations. Values outside every source range map to themselves
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+pub struct Map {
+  // Source start to destination start and length
   inner: BTreeMap<usize, Range>,
 }
 
@@ -66,71 +190,247 @@ impl Map {
     Ok((input, Self { inner: entries.into_iter().map(|(off, start, len)| (start, (off, len))).collect() }))
   }
 
-  fn translate(&self, (mut in_start, mut in_len): Range) -> impl IntoIterator<Item=Range> {
-    // TODO implement custom iterator
-    let mut outs = vec![];
-    let mut curs = self.inner.upper_bound(Bound::Included(&in_start));
-    // Until we go off the high end of the mappings...
-    'scan: {
-      while let Some((&map_start, &(map_off, map_len))) = curs.key_value() {
-        if in_start < map_start {
-          // There is a gap before the next mapping. This will never happen on the first iteration. Identity-map until that one starts
-          let out_len = map_start - in_start;
-          if in_len < out_len {
-            // There is room before the next mapping for the remaining range
-            outs.push((in_start, in_len));
-            break 'scan;
-          }
-          outs.push((in_start, out_len));
-          in_start += out_len;
-          in_len -= out_len;
-        }
+  pub fn get(&self, n: usize) -> usize {
+    match self.inner.range(..=n).next_back() {
+      Some((&map_start, &(map_off, map_len))) if n - map_start < map_len => n - map_start + map_off,
+      _ => n,
+    }
+  }
 
-        if in_start < map_start + map_len {
-          // This mapping intersects with our remaining range
-          let out_len = map_len - (in_start - map_start);
-          let out_start = in_start - map_start + map_off;
-          if in_len < out_len {
-            // There is room in this mapping for the remaining range
-            outs.push((out_start, in_len));
-            break 'scan;
-          }
-          outs.push((out_start, out_len));
-          in_start += out_len;
-          in_len -= out_len;
-        }
+  // The images of consecutive pieces of the range, in order
+  pub fn translate(&self, range: Range) -> Translate<'_> {
+    Translate { mappings: range_from_floor(&self.inner, range.0).peekable(), rest: range }
+  }
+
+  // The map that applies this one and then `next`
+  pub fn then(&self, next: &Map) -> Map {
+    let inner = self.pieces()
+      .flat_map(|(src, dest)| with_sources(src, next.translate(dest)))
+      .filter(|&(src, (dest, _))| src != dest)
+      .collect();
+    Map { inner }
+  }
+
+  // The map from destinations back to sources, if every value is the destination of exactly one source
+  pub fn inverse(&self) -> Option<Map> {
+    let mut pieces: Vec<_> = self.pieces().map(|(src, (dest, len))| (dest, (src, len))).collect();
+    pieces.sort_unstable();
+    let mut end = 0;
+    for &(dest, (_, len)) in &pieces {
+      if dest != end { return None; }
+      end = dest + len;
+    }
+    Some(Map { inner: pieces.into_iter().filter(|&(dest, (src, _))| src != dest).collect() })
+  }
+
+  // Every stretch of the domain, gaps included, with the range it maps to
+  fn pieces(&self) -> impl Iterator<Item=(usize, Range)> + '_ {
+    with_sources(0, self.translate((0, usize::MAX)))
+  }
+}
+
+// Pairs each translated piece with where it started, given the range started at `src`
+fn with_sources(src: usize, outs: impl Iterator<Item=Range>) -> impl Iterator<Item=(usize, Range)> {
+  outs.scan(src, |src, out| {
+    let piece = (*src, out);
+    *src += out.1;
+    Some(piece)
+  })
+}
+
+pub struct Translate<'a> {
+  mappings: Peekable<btree_map::Range<'a, usize, Range>>,
+  // The part of the range not yet translated
+  rest: Range,
+}
+
+impl Iterator for Translate<'_> {
+  type Item = Range;
+
+  fn next(&mut self) -> Option<Range> {
+    let (in_start, in_len) = self.rest;
+    if in_len == 0 { return None; }
+    let out = loop {
+      match self.mappings.peek() {
+        // We are off the high end of the mappings. Identity-map the remaining range
+        None => break (in_start, in_len),
+        // There is a gap before the next mapping. Identity-map until that one starts
+        Some(&(&map_start, _)) if in_start < map_start => break (in_start, in_len.min(map_start - in_start)),
+        // This mapping intersects with our remaining range
+        Some(&(&map_start, &(map_off, map_len))) if in_start < map_start + map_len =>
+          break (in_start - map_start + map_off, in_len.min(map_start + map_len - in_start)),
         // We're done with this mapping. Move to the next higher one
-        curs.move_next();
+        Some(_) => { self.mappings.next(); },
       }
-      // We are off the high end of the mappings. Identity-map the remaining range
-      outs.push((in_start, in_len));
-    }
-    outs
+    };
+    self.rest = (in_start + out.1, in_len - out.1);
+    Some(out)
   }
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_file;
+  use crate::{solution::Params as _, test_util::{sample_file, sample_input}};
+  use proptest::{prelude::*, collection::{btree_set, vec}};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_file("05a")), 35);
+    assert_eq!(part1(sample_input("05a")), Ok(35));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_file("05")), 84470622);
+    assert_eq!(part1(sample_input("05")), Ok(84470622));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_file("05a")), 46);
+    assert_eq!(part2(sample_input("05a")), Ok(46));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_file("05")), 26714516);
+    assert_eq!(part2(sample_input("05")), Ok(26714516));
+  }
+
+  #[test]
+  fn test_parse_error() {
+    let file = sample_file("05a").replace("light-to-temperature", "light-temperature");
+    let err = part1(file.as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 5, line 22, column 6: unexpected input (Tag) at \"-temperature map:\"");
+    let file = sample_file("05a").replacen("seeds: 79 14 55 13", "seeds: 79 14 55", 1);
+    assert_eq!(part2(file.as_bytes()).unwrap_err().to_string(), "day 5: seed ranges need an even number of values");
+  }
+
+  // Reference translation: look the value up in every entry
+  fn brute_translate(map: &Map, n: usize) -> usize {
+    map.inner.iter()
+      .find(|&(&start, &(_, len))| (start..start + len).contains(&n))
+      .map_or(n, |(&start, &(off, _))| n - start + off)
+  }
+
+  // Disjoint source ranges, drawn as pairs of distinct bounds
+  fn map_entries() -> impl Strategy<Value=BTreeMap<usize, Range>> {
+    (btree_set(0..60usize, 0..10), vec(0..60usize, 5))
+      .prop_map(|(bounds, offs)| {
+        let bounds: Vec<_> = bounds.into_iter().collect();
+        bounds.chunks_exact(2)
+          .zip(offs)
+          .map(|(pair, off)| (pair[0], (off, pair[1] - pair[0])))
+          .collect()
+      })
+  }
+
+  proptest! {
+    #[test]
+    fn test_translate_oracle(inner in map_entries(), start in 0..60usize, len in 1..30usize) {
+      let map = Map { inner };
+      let mut outs: Vec<_> = map.translate((start, len)).flat_map(|(s, l)| s..s + l).collect();
+      let mut expected: Vec<_> = (start..start + len).map(|n| brute_translate(&map, n)).collect();
+      outs.sort();
+      expected.sort();
+      prop_assert_eq!(outs, expected);
+    }
+
+    #[test]
+    fn test_lowest_location_oracle(maps in vec(map_entries(), 7), seeds in vec((0..60usize, 1..20usize), 1..4)) {
+      let maps: Vec<_> = maps.into_iter().map(|inner| Map { inner }).collect();
+      let cats = ["seed", "soil", "fertilizer", "water", "light", "temperature", "humidity", "location"];
+      let almanac = Almanac {
+        seeds: seeds.iter().flat_map(|&(start, len)| [start, len]).collect(),
+        maps: cats.windows(2)
+          .zip(maps.clone())
+          .map(|(pair, map)| (pair[0].to_owned(), BTreeMap::from([(pair[1].to_owned(), map)])))
+          .collect(),
+      };
+      let lowest = |seeds: Vec<usize>| seeds.into_iter()
+        .map(|n| maps.iter().fold(n, |n, map| brute_translate(map, n)))
+        .min().unwrap();
+      let params = Params::default();
+      prop_assert_eq!(lowest_location(&almanac, &params, single_seeds), Ok(lowest(almanac.seeds.clone())));
+      prop_assert_eq!(
+        lowest_location(&almanac, &params, seed_ranges),
+        Ok(lowest(seeds.iter().flat_map(|&(start, len)| start..start + len).collect())),
+      );
+    }
+
+    #[test]
+    fn test_compose_oracle(maps in vec(map_entries(), 1..4), ns in vec(0..80usize, 20)) {
+      let maps: Vec<_> = maps.into_iter().map(|inner| Map { inner }).collect();
+      let composed = maps.iter().fold(Map::default(), |composed, map| composed.then(map));
+      for n in ns {
+        let expected = maps.iter().fold(n, |n, map| brute_translate(map, n));
+        prop_assert_eq!(composed.get(n), expected);
+        prop_assert_eq!(maps[0].get(n), brute_translate(&maps[0], n));
+      }
+    }
+
+    #[test]
+    fn test_inverse_oracle(inner in permuted_entries(), ns in vec(0..80usize, 20)) {
+      let map = Map { inner };
+      let inverse = map.inverse().unwrap();
+      for n in ns {
+        prop_assert_eq!(inverse.get(map.get(n)), n);
+        prop_assert_eq!(map.get(inverse.get(n)), n);
+      }
+    }
+  }
+
+  // A span cut into pieces that are then laid out again in a shuffled order, so that the map is a bijection
+  fn permuted_entries() -> impl Strategy<Value=BTreeMap<usize, Range>> {
+    btree_set(0..60usize, 0..10)
+      .prop_flat_map(|bounds| {
+        let bounds: Vec<_> = bounds.into_iter().collect();
+        let pieces: Vec<_> = bounds.windows(2).map(|pair| (pair[0], pair[1] - pair[0])).collect();
+        (Just(bounds.first().copied().unwrap_or_default()), Just(pieces).prop_shuffle())
+      })
+      .prop_map(|(mut dest, pieces)| {
+        pieces.into_iter()
+          .map(|(start, len)| {
+            dest += len;
+            (start, (dest - len, len))
+          })
+          .collect()
+      })
+  }
+
+  #[test]
+  fn test_composed() {
+    let almanac = parse_almanac(&sample_file("05a")).unwrap();
+    let composed = almanac.composed("seed", "location").unwrap();
+    assert_eq!([79, 14, 55, 13].map(|seed| composed.get(seed)), [82, 43, 86, 35]);
+    assert_eq!(composed.then(&Map::default()), composed);
+    assert_eq!(almanac.maps["seed"]["soil"].inverse().unwrap().get(81), 79);
+    // 5 and 6 would both have two sources
+    assert_eq!(Map { inner: BTreeMap::from([(0, (5, 2))]) }.inverse(), None);
+  }
+
+  #[test]
+  fn test_category_graph() {
+    // Sections in any order, with a branch off the main chain
+    let input = "seeds: 1 5\n\nb-to-c map:\n10 0 10\n\na-to-b map:\n0 1 3\n\na-to-x map:\n7 0 2\n";
+    let almanac = parse_almanac(input).unwrap();
+    assert_eq!(almanac.chain("a", "c").unwrap().len(), 2);
+    assert_eq!(almanac.translate("a", "c", 2), Ok(11));
+    assert_eq!(almanac.translate("a", "x", 1), Ok(8));
+    assert_eq!(almanac.translate("c", "c", 3), Ok(3));
+    assert_eq!(almanac.translate("b", "a", 3).unwrap_err().to_string(), "no chain of maps from b to a");
+
+    let mut params = Params::default();
+    params.set("from", "a").unwrap();
+    params.set("to", "c").unwrap();
+    assert_eq!(Solver::part1(&almanac, &params), Ok(Answer(10)));
+    assert_eq!(Solver::part1(&almanac, &Params::default()).unwrap_err().to_string(), "no chain of maps from seed to location");
+    let almanac = parse_almanac("seeds: 1 0\n\na-to-b map:\n0 1 3\n\nb-to-c map:\n10 0 10\n").unwrap();
+    assert_eq!(Solver::part2(&almanac, &params).unwrap_err().to_string(), "day 5: no seeds");
+
+    let err = parse_almanac(&format!("{}\nc-to-a map:\n", input)).unwrap_err();
+    assert_eq!(err.to_string(), "day 5, line 12, column 1: maps form a cycle: a -> b -> c -> a at \"c-to-a map:\"");
+    let err = parse_almanac(&format!("{}\nb-to-c map:\n", input)).unwrap_err();
+    assert_eq!(err.to_string(), "day 5, line 12, column 1: second map from b to c at \"b-to-c map:\"");
+    // The first b-to-c header is inside a longer one
+    let err = parse_almanac("seeds: 1\n\nxb-to-c map:\n\nb-to-c map:\n\nb-to-c map:\n").unwrap_err();
+    assert_eq!(err.to_string(), "day 5, line 7, column 1: second map from b to c at \"b-to-c map:\"");
   }
 }
@@ -1,34 +1,60 @@
-use std::num::ParseIntError;
+use std::io::BufRead;
 
-use crate::util::usize;
+use crate::{solution::{read_input, Solution, Answer}, error::{Result, parse_all}};
 
 use itertools::Itertools;
-use nom::{IResult, character::complete::{char, line_ending, digit1}, bytes::complete::tag, multi::many1, sequence::{delimited, preceded, terminated, pair}, combinator::{eof, verify, map_res, map}};
-
-pub fn part1(file: String) -> usize { process(file, parse_num_list) }
-pub fn part2(file: String) -> usize { process(file, parse_big_num) }
-
-fn process(file: String, parse_nums: impl Fn(&str) -> IResult<&str, Vec<usize>>) -> usize {
-  let (_, races) = parse(&file, parse_nums).unwrap();
-  races.into_iter()
-    .map(|(time, record)| {
-      let mut lo = 0;
-      let mut hi = time/2;
-      while hi > lo {
-        let mid = (hi + lo) / 2;
-        let score = mid * (time - mid);
-        if score > record {
-          hi = mid;
-        } else {
-          lo = mid + 1;
-        }
-      }
-      time + 1 - 2 * lo
+use nom::{IResult, character::complete::{char, line_ending, digit1}, bytes::complete::tag, multi::many1, sequence::{delimited, preceded, terminated, pair}, combinator::{eof, verify, map}};
+use num::{BigUint, Zero};
+
+pub fn part1(input: impl BufRead) -> Result<BigUint> { process(&read_input(input)?, parse_num_list) }
+pub fn part2(input: impl BufRead) -> Result<BigUint> { process(&read_input(input)?, parse_big_num) }
+pub fn part1_file(file: String) -> Result<BigUint> { part1(file.as_bytes()) }
+pub fn part2_file(file: String) -> Result<BigUint> { part2(file.as_bytes()) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 6;
+  // The two parts read the numbers differently, so parsing waits for the part
+  type Input = String;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> { Ok(input.to_owned()) }
+  fn part1(file: &Self::Input, _: &()) -> Result<Answer> { process(file, parse_num_list)?.try_into() }
+  fn part2(file: &Self::Input, _: &()) -> Result<Answer> { process(file, parse_big_num)?.try_into() }
+}
+
+fn process(file: &str, parse_nums: impl Fn(&str) -> IResult<&str, Vec<BigUint>>) -> Result<BigUint> {
+  let races = parse_all(Solver::DAY, 0, file, |input| parse(input, &parse_nums))?;
+  Ok(races.iter()
+    .map(|(time, record)| match winning_holds(time, record) {
+      Some([lo, hi]) => hi - lo + 1u32,
+      None => BigUint::zero(),
     })
-    .product()
+    .product())
+}
+
+// The first and last hold times that beat the record. Holding for h travels h(time - h), so these are the integers
+// strictly between the roots of h² - time·h + record
+pub fn winning_holds(time: &BigUint, record: &BigUint) -> Option<[BigUint; 2]> {
+  let wins = |hold: &BigUint| hold * (time - hold) > *record;
+  let best = time / 2u32;
+  if !wins(&best) { return None; }
+
+  // The lower root is (time - √(time² - 4·record)) / 2. Flooring the square root can leave the estimate a step off
+  let disc = time * time - record * 4u32;
+  let mut lo = (time - disc.sqrt()) / 2u32;
+  while !wins(&lo) {
+    lo += 1u32;
+  }
+  while !lo.is_zero() && wins(&(&lo - 1u32)) {
+    lo -= 1u32;
+  }
+  let hi = time - &lo;
+  Some([lo, hi])
 }
 
-fn parse(input: &str, parse_nums: impl Fn(&str) -> IResult<&str, Vec<usize>>) -> IResult<&str, Vec<(usize, usize)>> {
+fn parse(input: &str, parse_nums: impl Fn(&str) -> IResult<&str, Vec<BigUint>>) -> IResult<&str, Vec<(BigUint, BigUint)>> {
   map(
     verify(
       terminated(
@@ -52,42 +78,85 @@ fn parse(input: &str, parse_nums: impl Fn(&str) -> IResult<&str, Vec<usize>>) ->
   )(input)
 }
 
-fn parse_num_list(input: &str) -> IResult<&str, Vec<usize>> {
-  many1(preceded(many1(char(' ')), usize))(input)
+fn parse_num_list(input: &str) -> IResult<&str, Vec<BigUint>> {
+  many1(preceded(many1(char(' ')), map(digit1, big_num)))(input)
 }
 
-fn parse_big_num(input: &str) -> IResult<&str, Vec<usize>> {
-  map_res(
+// All the numbers on the line run together into one
+fn parse_big_num(input: &str) -> IResult<&str, Vec<BigUint>> {
+  map(
     many1(preceded(many1(char(' ')), digit1)),
-    |chunks| -> Result<_, ParseIntError> {
-      let chunks: String = chunks.into_iter().collect();
-      Ok(vec![chunks.parse()?])
-    },
+    |chunks: Vec<&str>| vec![big_num(&chunks.concat())],
   )(input)
 }
 
+fn big_num(digits: &str) -> BigUint {
+  BigUint::parse_bytes(digits.as_bytes(), 10).unwrap()
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_file;
+  use crate::test_util::{sample_file, sample_input};
+  use proptest::prelude::*;
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_file("06a")), 288);
+    assert_eq!(part1(sample_input("06a")), Ok(288u32.into()));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_file("06")), 2374848);
+    assert_eq!(part1(sample_input("06")), Ok(2374848u32.into()));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_file("06a")), 71503);
+    assert_eq!(part2(sample_input("06a")), Ok(71503u32.into()));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_file("06")), 39132886);
+    assert_eq!(part2(sample_input("06")), Ok(39132886u32.into()));
+  }
+
+  #[test]
+  fn test_winning_holds() {
+    let races = parse_all(Solver::DAY, 0, &sample_file("06a"), |input| parse(input, parse_num_list)).unwrap();
+    let holds: Vec<_> = races.iter().map(|(time, record)| winning_holds(time, record)).collect();
+    assert_eq!(holds, [[2u32, 5], [4, 11], [11, 19]].map(|hold| Some(hold.map(BigUint::from))));
+    // Holding for 2 ties the record rather than beating it
+    assert_eq!(winning_holds(&4u32.into(), &4u32.into()), None);
+    assert_eq!(winning_holds(&0u32.into(), &0u32.into()), None);
+    assert_eq!(winning_holds(&1u32.into(), &0u32.into()), None);
+
+    let time = big_num(&"9".repeat(60));
+    // Only the two holds either side of half the time beat this
+    let file = format!("Time: {}\nDistance: {}\n", time, &time * &time / 4u32 - 1u32);
+    assert_eq!(Solver::part2(&file, &()), Ok(Answer(2)));
+    let file = format!("Time: {} {}\nDistance: {} {}\n", time, 5, 1, 0);
+    assert_eq!(process(&file, parse_num_list), Ok(&time * 4u32 - 4u32));
+    assert!(Solver::part1(&file, &()).is_err());
+  }
+
+  proptest! {
+    #[test]
+    fn test_winning_holds_oracle(time in 0..200u32, record in 0..12000u32) {
+      let holds: Vec<_> = (0..=time).filter(|&hold| hold * (time - hold) > record).collect();
+      let expected = holds.first().map(|&lo| [lo, *holds.last().unwrap()].map(BigUint::from));
+      prop_assert_eq!(winning_holds(&time.into(), &record.into()), expected);
+    }
+
+    #[test]
+    fn test_winning_holds_bounds(time in "[1-9][0-9]{20,60}", frac in 0..1000u32) {
+      let time = big_num(&time);
+      let record = &time * &time / 4u32 * frac / 1000u32;
+      let [lo, hi] = winning_holds(&time, &record).unwrap();
+      let dist = |hold: &BigUint| hold * (&time - hold);
+      prop_assert!(dist(&lo) > record && dist(&hi) > record);
+      prop_assert!(lo.is_zero() || dist(&(&lo - 1u32)) <= record);
+      prop_assert!(hi == time || dist(&(&hi + 1u32)) <= record);
+      prop_assert_eq!(&lo + &hi, time);
+    }
   }
 }
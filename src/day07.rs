@@ -1,145 +1,331 @@
-use crate::util::usize;
+use std::{fmt::{self, Display}, io::BufRead};
 
-use enum_map::{Enum, EnumMap};
+use crate::{util::usize, solution::{read_input, joined_lines, Solution, Answer}, error::{Error, Result, parse_lines}};
+
+use itertools::Itertools;
 use nom::{IResult, character::complete::{char, one_of}, multi::count, sequence::{terminated, separated_pair}, combinator::{eof, map}};
 
-pub fn part1(lines: impl Iterator<Item=String>) -> usize { process(lines, false) }
-pub fn part2(lines: impl Iterator<Item=String>) -> usize { process(lines, true) }
+pub fn part1(input: impl BufRead) -> Result<usize> { process(read_input(input)?.lines(), &Rules::standard()) }
+pub fn part2(input: impl BufRead) -> Result<usize> { process(read_input(input)?.lines(), &Rules::jokers()) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part2(joined_lines(lines)) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 7;
+  // Whether J is a jack or a joker depends on the part, so parsing waits for the part
+  type Input = String;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> { Ok(input.to_owned()) }
+  fn part1(text: &Self::Input, _: &()) -> Result<Answer> { process(text.lines(), &Rules::standard()).map(Answer::from) }
+  fn part2(text: &Self::Input, _: &()) -> Result<Answer> { process(text.lines(), &Rules::jokers()).map(Answer::from) }
+}
+
+fn process(lines: impl Iterator<Item=impl AsRef<str>>, rules: &Rules) -> Result<usize> {
+  Ok(ranked(lines, rules)?.into_iter()
+    .map(|ranked| ranked.winnings)
+    .sum())
+}
 
-fn process(lines: impl Iterator<Item=String>, jokers: bool) -> usize {
-  let mut hands: Vec<_> = lines
-    .map(|line| Hand::parse(&line, jokers).unwrap().1)
-    .collect();
+// Every hand from weakest to strongest, with what it wins
+pub fn ranked(lines: impl Iterator<Item=impl AsRef<str>>, rules: &Rules) -> Result<Vec<Ranked>> {
+  let mut hands = parse_lines(Solver::DAY, lines, |line| rules.parse_hand(line))?;
 
   hands.sort();
-  
-  hands.into_iter()
+
+  Ok(hands.into_iter()
     .enumerate()
-    .map(|(i, hand)| hand.bid * (i+1))
-    .sum()
+    .map(|(i, hand)| Ranked { rank: i + 1, winnings: hand.bid * (i + 1), hand })
+    .collect())
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Enum, Clone, Copy, Debug)]
-enum Card {
-  Joker, Two, Three, Four, Five, Six, Seven, Eight, Nine, Ten, Jack, Queen, King, Ace,
+pub struct Ranked {
+  pub hand: Hand,
+  pub rank: usize,
+  pub winnings: usize,
 }
 
-impl Card {
-  fn parse(input: &str, jokers: bool) -> IResult<&str, Self> {
-    map(
-      one_of("23456789TJQKA"),
-      |c| match c {
-        '2' => Card::Two,
-        '3' => Card::Three,
-        '4' => Card::Four,
-        '5' => Card::Five,
-        '6' => Card::Six,
-        '7' => Card::Seven,
-        '8' => Card::Eight,
-        '9' => Card::Nine,
-        'T' => Card::Ten,
-        'J' => if jokers { Card::Joker } else { Card::Jack },
-        'Q' => Card::Queen,
-        'K' => Card::King,
-        'A' => Card::Ace,
-        _ => unreachable!(),
-      }
-    )(input)
-  }
+// How a hand came by its type and its place in the ranking
+pub struct Explanation<'a> {
+  pub ranked: &'a Ranked,
+  // The cards with each wild card replaced by what it stands for
+  pub substituted: String,
+  pub natural_type: HandType,
+  // What decides the hand's order against the hands ranked just below and just above it
+  pub beats: Option<TieBreak<'a>>,
+  pub loses_to: Option<TieBreak<'a>>,
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
-struct Hand {
-  hand_type: HandType,
-  cards: [Card; 5],
-  bid: usize,
+pub struct TieBreak<'a> {
+  pub other: &'a Hand,
+  pub decided_by: DecidedBy,
 }
 
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
-enum HandType {
-  HighCard, OnePair, TwoPair, ThreeOfAKind, FullHouse, FourOfAKind, FiveOfAKind,
+#[derive(PartialEq, Eq, Debug)]
+pub enum DecidedBy {
+  Type,
+  // The index of the first card that differs
+  Card(usize),
+  // The cards are the same, so the order is arbitrary
+  Nothing,
 }
 
-impl HandType {
-  fn calc(cards: &[Card; 5]) -> HandType {
-    let mut counts: EnumMap<Card, usize> = EnumMap::default();
-    for &card in cards {
-      counts[card] += 1;
+// `ranked` must come from `ranked` under the same rules
+pub fn explain<'a>(ranked: &'a [Ranked], rules: &Rules) -> Vec<Explanation<'a>> {
+  let tie_break = |hand: &Hand, other: &'a Ranked| TieBreak {
+    other: &other.hand,
+    decided_by: if hand.hand_type != other.hand.hand_type {
+      DecidedBy::Type
+    } else {
+      hand.strengths.iter()
+        .zip(&other.hand.strengths)
+        .position(|(a, b)| a != b)
+        .map_or(DecidedBy::Nothing, DecidedBy::Card)
+    },
+  };
+  ranked.iter()
+    .enumerate()
+    .map(|(i, r)| {
+      let strengths = &r.hand.strengths;
+      Explanation {
+        ranked: r,
+        substituted: rules.substituted(strengths).map(|card| rules.label(card)).collect(),
+        natural_type: rules.natural_type(strengths),
+        beats: i.checked_sub(1).map(|below| tie_break(&r.hand, &ranked[below])),
+        loses_to: ranked.get(i + 1).map(|above| tie_break(&r.hand, above)),
+      }
+    })
+    .collect()
+}
+
+impl Display for Explanation<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let hand = &self.ranked.hand;
+    write!(f, "{} {}: {}", self.ranked.rank, hand.cards, self.natural_type)?;
+    if self.substituted != hand.cards {
+      write!(f, " -> {} as {}", hand.hand_type, self.substituted)?;
+    }
+    for (verb, tie_break) in [("beats", &self.beats), ("loses to", &self.loses_to)] {
+      if let Some(TieBreak { other, decided_by }) = tie_break {
+        write!(f, "; {} {} ", verb, other.cards)?;
+        match *decided_by {
+          DecidedBy::Type => write!(f, "on type")?,
+          DecidedBy::Card(i) => write!(f, "on card {} ({} vs {})", i + 1, hand.cards.chars().nth(i).unwrap(), other.cards.chars().nth(i).unwrap())?,
+          DecidedBy::Nothing => write!(f, "by bid, being the same cards")?,
+        }
+      }
     }
+    Ok(())
+  }
+}
 
-    let jokers = counts[Card::Joker];
+// A variant of the game: which labels there are and how they rank, which of them are wild, and how many cards a hand has
+pub struct Rules {
+  // Labels from weakest to strongest
+  order: String,
+  // Indexed by strength
+  wild: Vec<bool>,
+  hand_size: usize,
+}
 
-    let mut metacounts = [0usize; 6];
-    for (card, count) in counts.into_iter() {
-      if card != Card::Joker {
-        metacounts[count] += 1;
-      }
+impl Rules {
+  pub fn new(order: &str, wild: &str, hand_size: usize) -> Result<Self> {
+    if let Some(dup) = order.chars().duplicates().next() {
+      return Err(Error::Usage(format!("card {:?} is ranked twice", dup)));
+    }
+    if let Some(unknown) = wild.chars().find(|&c| !order.contains(c)) {
+      return Err(Error::Usage(format!("wild card {:?} is not ranked", unknown)));
     }
+    if hand_size == 0 {
+      return Err(Error::Usage("hands need at least one card".to_owned()));
+    }
+    Ok(Self { order: order.to_owned(), wild: order.chars().map(|c| wild.contains(c)).collect(), hand_size })
+  }
 
-    match jokers + metacounts.iter().rposition(|&n| n > 0).unwrap() {
-      5 => HandType::FiveOfAKind,
-      4 => HandType::FourOfAKind,
-      3 => match (jokers, metacounts[2]) {
-        (2, 0) => HandType::ThreeOfAKind,
-        (1, 2) => HandType::FullHouse,
-        (1, 1) => HandType::ThreeOfAKind,
-        (0, 1) => HandType::FullHouse,
-        (0, 0) => HandType::ThreeOfAKind,
-        _ => unreachable!(),
-      },
-      2 => match (jokers, metacounts[2]) {
-        (1, 0) => HandType::OnePair,
-        (0, 2) => HandType::TwoPair,
-        (0, 1) => HandType::OnePair,
-        _ => unreachable!(),
-      }
-      1 => HandType::HighCard,
-      _ => unreachable!(),
+  pub fn standard() -> Self {
+    Self::new("23456789TJQKA", "", 5).unwrap()
+  }
+
+  // J is wild, and the weakest card when breaking ties
+  pub fn jokers() -> Self {
+    Self::new("J23456789TQKA", "J", 5).unwrap()
+  }
+
+  fn strength(&self, label: char) -> usize {
+    self.order.chars().position(|c| c == label).unwrap()
+  }
+
+  fn label(&self, strength: usize) -> char {
+    self.order.chars().nth(strength).unwrap()
+  }
+
+  fn hand_type(&self, strengths: &[usize]) -> HandType {
+    self.shape(self.substituted(strengths))
+  }
+
+  // The type the cards would have if nothing were wild
+  fn natural_type(&self, strengths: &[usize]) -> HandType {
+    self.shape(strengths.iter().copied())
+  }
+
+  fn shape(&self, strengths: impl Iterator<Item=usize>) -> HandType {
+    let mut counts = vec![0; self.wild.len()];
+    for card in strengths {
+      counts[card] += 1;
     }
+    let mut shape: Vec<_> = counts.into_iter().filter(|&n| n > 0).collect();
+    shape.sort_unstable_by(|a, b| b.cmp(a));
+    HandType(shape)
+  }
+
+  // What each card stands for. Wild cards join the largest group, which always gives the strongest type, picking the
+  // strongest such group. With nothing but wild cards in hand they become the strongest card that isn't wild
+  fn substituted<'a>(&'a self, strengths: &'a [usize]) -> impl Iterator<Item=usize> + 'a {
+    let target = strengths.iter()
+      .filter(|&&card| !self.wild[card])
+      .counts()
+      .into_iter()
+      .max_by_key(|&(&card, n)| (n, card))
+      .map(|(&card, _)| card)
+      .or_else(|| self.wild.iter().rposition(|&wild| !wild));
+    strengths.iter().map(move |&card| match target {
+      Some(target) if self.wild[card] => target,
+      _ => card,
+    })
   }
-}
 
-impl Hand {
-  fn parse(input: &str, jokers: bool) -> IResult<&str, Self> {
+  fn parse_hand<'a>(&self, input: &'a str) -> IResult<&'a str, Hand> {
     map(
       terminated(
         separated_pair(
-          count(|input| Card::parse(input, jokers), 5),
+          count(one_of(self.order.as_str()), self.hand_size),
           char(' '),
           usize,
         ),
         eof,
       ),
       |(cards, bid)| {
-        let cards = cards.try_into().unwrap();
-        Self { cards, bid, hand_type: HandType::calc(&cards) }
+        let strengths: Vec<_> = cards.iter().map(|&c| self.strength(c)).collect();
+        Hand { hand_type: self.hand_type(&strengths), strengths, bid, cards: cards.into_iter().collect() }
       },
     )(input)
   }
 }
 
+// Hands compare by type, then card by card
+#[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub struct Hand {
+  pub hand_type: HandType,
+  strengths: Vec<usize>,
+  pub bid: usize,
+  pub cards: String,
+}
+
+// The sizes of the groups of matching cards, largest first. Comparing these in order ranks the usual categories, from
+// high card ([1, 1, 1, 1, 1]) up to five of a kind ([5]), and extends to any hand size
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+pub struct HandType(pub Vec<usize>);
+
+impl Display for HandType {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self.0.as_slice() {
+      [5] => f.write_str("five of a kind"),
+      [4, 1] => f.write_str("four of a kind"),
+      [3, 2] => f.write_str("full house"),
+      [3, 1, 1] => f.write_str("three of a kind"),
+      [2, 2, 1] => f.write_str("two pair"),
+      [2, 1, 1, 1] => f.write_str("one pair"),
+      [1, 1, 1, 1, 1] => f.write_str("high card"),
+      shape => write!(f, "{}", shape.iter().join("-")),
+    }
+  }
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::test_util::{sample_file, sample_input};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("07a")), 6440);
+    assert_eq!(part1(sample_input("07a")), Ok(6440));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("07")), 250058342);
+    assert_eq!(part1(sample_input("07")), Ok(250058342));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("07a")), 5905);
+    assert_eq!(part2(sample_input("07a")), Ok(5905));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("07")), 250506580);
+    assert_eq!(part2(sample_input("07")), Ok(250506580));
+  }
+
+  #[test]
+  fn test_ranked() {
+    let hands = ranked(sample_file("07a").lines(), &Rules::jokers()).unwrap();
+    let summary: Vec<_> = hands.iter()
+      .map(|r| (r.rank, r.hand.cards.as_str(), r.hand.hand_type.to_string(), r.winnings))
+      .collect();
+    assert_eq!(summary, [
+      (1, "32T3K", "one pair".to_owned(), 765),
+      (2, "KK677", "two pair".to_owned(), 28 * 2),
+      (3, "T55J5", "four of a kind".to_owned(), 684 * 3),
+      (4, "QQQJA", "four of a kind".to_owned(), 483 * 4),
+      (5, "KTJJT", "four of a kind".to_owned(), 220 * 5),
+    ]);
+  }
+
+  #[test]
+  fn test_variant_rules() {
+    // Three-card hands of a short deck where both 1 and 2 are wild
+    let rules = Rules::new("12ABC", "12", 3).unwrap();
+    let hands = ranked(["ABC 1", "CC2 10", "12B 100", "AAB 1000"].into_iter(), &rules).unwrap();
+    let summary: Vec<_> = hands.iter().map(|r| (r.hand.cards.as_str(), r.hand.hand_type.clone())).collect();
+    assert_eq!(summary, [
+      ("ABC", HandType(vec![1, 1, 1])),
+      ("AAB", HandType(vec![2, 1])),
+      ("12B", HandType(vec![3])),
+      ("CC2", HandType(vec![3])),
+    ]);
+    assert_eq!(hands[2].hand.hand_type.to_string(), "3");
+    assert!(ranked(["ABCA 1"].into_iter(), &rules).is_err());
+
+    assert!(Rules::new("AA", "", 5).is_err());
+    assert!(Rules::new("AB", "C", 5).is_err());
+    assert!(Rules::new("AB", "", 0).is_err());
+  }
+
+  #[test]
+  fn test_explain() {
+    let rules = Rules::jokers();
+    let hands = ranked(sample_file("07a").lines(), &rules).unwrap();
+    let explanations = explain(&hands, &rules);
+    let rendered: Vec<_> = explanations.iter().map(Explanation::to_string).collect();
+    assert_eq!(rendered, [
+      "1 32T3K: one pair; loses to KK677 on type",
+      "2 KK677: two pair; beats 32T3K on type; loses to T55J5 on type",
+      "3 T55J5: three of a kind -> four of a kind as T5555; beats KK677 on type; loses to QQQJA on card 1 (T vs Q)",
+      "4 QQQJA: three of a kind -> four of a kind as QQQQA; beats T55J5 on card 1 (Q vs T); loses to KTJJT on card 1 (Q vs K)",
+      "5 KTJJT: two pair -> four of a kind as KTTTT; beats QQQJA on card 1 (K vs Q)",
+    ]);
+    assert_eq!(explanations[4].natural_type, HandType(vec![2, 2, 1]));
+    assert_eq!(explanations[4].beats.as_ref().unwrap().decided_by, DecidedBy::Card(0));
+
+    // Ties between equal groups go to the stronger card, and a hand of jokers becomes aces
+    let hands = ranked(["JJJJJ 1", "2J2J3 2", "2J2J3 3", "23J3J 4"].into_iter(), &rules).unwrap();
+    let explanations = explain(&hands, &rules);
+    let substituted: Vec<_> = explanations.iter().map(|e| e.substituted.as_str()).collect();
+    assert_eq!(substituted, ["22223", "22223", "23333", "AAAAA"]);
+    assert_eq!(explanations[0].loses_to.as_ref().unwrap().decided_by, DecidedBy::Nothing);
+    assert_eq!(explanations[2].beats.as_ref().unwrap().decided_by, DecidedBy::Card(1));
+    assert!(explanations[1].to_string().ends_with("beats 2J2J3 by bid, being the same cards; loses to 23J3J on card 2 (J vs 3)"));
   }
 }
@@ -1,152 +1,391 @@
-use std::{collections::{HashMap, HashSet}, mem::{swap, take}};
+use std::{collections::{BTreeSet, HashMap, HashSet}, fmt::Write, io::BufRead, iter};
 
 use enum_map::{enum_map, Enum, EnumMap};
-use itertools::iproduct;
-use nom::{IResult, character::complete::{char, one_of, line_ending}, multi::{count, many1}, sequence::{terminated, separated_pair, delimited}, combinator::{eof, map}, bytes::complete::tag};
-use num::Integer;
-
-pub fn part1(file: String) -> usize {
-  let (route, map) = parse(&file).unwrap().1;
-  let start = parse_location("AAA").unwrap().1;
-  let goal = parse_location("ZZZ").unwrap().1;
-
-  let mut jumps: HashMap<Location, Result<usize, Location>> = map.keys()
-    .map(|&jump_start| {
-      let mut loc = jump_start;
-      for steps in 0..route.len() {
-        if loc == goal { return (jump_start, Ok(steps)); }
-        loc = map[&loc][route[steps]];
-      }
-      (jump_start, if loc == goal { Ok(route.len()) } else { Err(loc) })
-    })
-    .collect();
+use itertools::{iproduct, Itertools};
+use nom::{IResult, character::complete::{char, one_of, line_ending, alphanumeric1}, multi::{count, many1}, sequence::{terminated, separated_pair, delimited}, combinator::{eof, map}, bytes::complete::tag};
+use num::{BigInt, BigUint, Integer, Zero, integer::ExtendedGcd};
+
+use crate::{util::TryInsert, solution::{self, read_input, parse_param, unknown_param, Solution, Answer}, error::{self, Error, parse_all}};
+
+pub fn part1(input: impl BufRead) -> error::Result<usize> { steps_to_goal(&parse_network(&read_input(input)?)?, &Params::default()) }
+pub fn part2(input: impl BufRead) -> error::Result<BigUint> { ghost_steps_to_goal(&parse_network(&read_input(input)?)?, &Params::default()) }
+pub fn part1_file(file: String) -> error::Result<usize> { part1(file.as_bytes()) }
+pub fn part2_file(file: String) -> error::Result<BigUint> { part2(file.as_bytes()) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 8;
+  type Input = Network;
+  type Params = Params;
+
+  fn parse(input: &str) -> error::Result<Self::Input> { parse_network(input) }
+  fn part1(network: &Self::Input, params: &Params) -> error::Result<Answer> { Ok(steps_to_goal(network, params)?.into()) }
+  fn part2(network: &Self::Input, params: &Params) -> error::Result<Answer> { ghost_steps_to_goal(network, params)?.try_into() }
+}
+
+// Part 1 walks from one named node to another. In part 2 the ghosts start on every node whose name ends with
+// `ghost_start` and stop when they all stand on one ending with `ghost_end`
+pub struct Params {
+  pub from: String,
+  pub to: String,
+  pub ghost_start: String,
+  pub ghost_end: String,
+}
+
+impl Default for Params {
+  fn default() -> Self {
+    Self { from: "AAA".to_owned(), to: "ZZZ".to_owned(), ghost_start: "A".to_owned(), ghost_end: "Z".to_owned() }
+  }
+}
+
+impl solution::Params for Params {
+  const NAMES: &'static [&'static str] = &["from", "to", "ghost_start", "ghost_end"];
 
-  let mut jump_size = route.len();
-  loop {
-    if let Ok(steps) = jumps[&start] {
-      break steps;
+  fn set(&mut self, name: &str, value: &str) -> error::Result<()> {
+    match name {
+      "from" => self.from = parse_param(name, value)?,
+      "to" => self.to = parse_param(name, value)?,
+      "ghost_start" => self.ghost_start = parse_param(name, value)?,
+      "ghost_end" => self.ghost_end = parse_param(name, value)?,
+      _ => return unknown_param(name),
     }
-    jumps = jumps.iter()
-      .map(|(&jump_start, &outcome)| (jump_start, match outcome {
-        Ok(steps) => Ok(steps),
-        Err(jump_end) => match jumps[&jump_end] {
-          Ok(steps) => Ok(jump_size + steps),
-          Err(new_jump_end) => Err(new_jump_end),
-        },
-      }))
-      .collect();
-    jump_size <<= 1;
+    Ok(())
   }
 }
 
-#[derive(Default, Clone)]
-struct SparseRange {
-  vals: HashSet<usize>,
-  len: usize,
+fn steps_to_goal(network: &Network, Params { from, to, .. }: &Params) -> error::Result<usize> {
+  let start = network.node(from).ok_or_else(|| Error::Usage(format!("no node {}", from)))?;
+  network.steps_to_end(start, |name| name == to)
+    .ok_or_else(|| Error::Usage(format!("{} is never reached from {}", to, from)))
+}
+
+fn ghost_steps_to_goal(network: &Network, Params { ghost_start, ghost_end, .. }: &Params) -> error::Result<BigUint> {
+  network.ghost_steps(|name| name.ends_with(ghost_start.as_str()), |name| name.ends_with(ghost_end.as_str()))
 }
-impl SparseRange {
-  fn append(&mut self, other: &Self) {
-    self.vals.extend(other.vals.iter().map(|n| n + self.len));
-    self.len += other.len;
+
+pub type Node = usize;
+
+#[derive(Debug)]
+pub struct Network {
+  route: Vec<Dir>,
+  // Node names in the order the map lists them, and the reverse
+  names: Vec<String>,
+  ids: HashMap<String, Node>,
+  map: Vec<EnumMap<Dir, Node>>,
+}
+
+// Where a walk from some node stands on an end. The walk is cut into passes through the route, and it loops once a
+// pass starts from the same node as an earlier one
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Walk {
+  // The node each pass starts from, up to the first repeat
+  pub passes: Vec<Node>,
+  // The pass that the walk returns to after the last one
+  pub loop_pass: usize,
+  pub pass_len: usize,
+  // The steps before the loop that end on an end, then those within the loop counted from its start
+  pub ends: Vec<usize>,
+  pub loop_ends: Vec<usize>,
+}
+
+impl Walk {
+  pub fn loop_start(&self) -> usize {
+    self.loop_pass * self.pass_len
+  }
+
+  pub fn loop_len(&self) -> usize {
+    (self.passes.len() - self.loop_pass) * self.pass_len
+  }
+
+  pub fn is_end(&self, step: usize) -> bool {
+    if step < self.loop_start() {
+      self.ends.binary_search(&step).is_ok()
+    } else {
+      self.loop_ends.binary_search(&((step - self.loop_start()) % self.loop_len())).is_ok()
+    }
+  }
+
+  pub fn first_end(&self) -> Option<usize> {
+    self.ends.first().copied()
+      .or_else(|| self.loop_ends.first().map(|end| end + self.loop_start()))
+  }
+}
+
+impl Network {
+  pub fn node(&self, name: &str) -> Option<Node> {
+    self.ids.get(name).copied()
+  }
+
+  pub fn name(&self, node: Node) -> &str {
+    &self.names[node]
+  }
+
+  // In the order the map lists them
+  pub fn nodes(&self) -> impl Iterator<Item=Node> {
+    0..self.names.len()
+  }
+
+  pub fn walk(&self, start: Node, is_end: impl Fn(&str) -> bool) -> Walk {
+    self.walk_flagged(start, &self.flags(is_end))
+  }
+
+  // The fewest steps from `start` to a node that `is_end` accepts, if it ever reaches one
+  pub fn steps_to_end(&self, start: Node, is_end: impl Fn(&str) -> bool) -> Option<usize> {
+    self.walk(start, is_end).first_end()
+  }
+
+  // A ghost walks from every node that `is_start` accepts, all at once. The fewest steps until they all stand on nodes
+  // that `is_end` accepts
+  pub fn ghost_steps(&self, is_start: impl Fn(&str) -> bool, is_end: impl Fn(&str) -> bool) -> error::Result<BigUint> {
+    self.ghost_walks(is_start, &self.flags(is_end))
+      .iter()
+      .map(CyclicSet::new)
+      .reduce(CyclicSet::intersection)
+      .ok_or_else(|| Error::input(Solver::DAY, "no ghost starts"))?
+      .min()
+      .ok_or_else(|| Error::input(Solver::DAY, "the ghosts never all stand on ends at once"))
+  }
+
+  // The map in Graphviz form, with one edge per direction out of each node. Ghost starts are boxed and labelled with
+  // how their walks loop, ends are drawn with a double outline, and the edges each ghost keeps taking once it loops are
+  // drawn bold in that ghost's colour
+  pub fn network_dot(&self, is_start: impl Fn(&str) -> bool, is_end: impl Fn(&str) -> bool) -> String {
+    let is_end = self.flags(is_end);
+    let walks = self.ghost_walks(is_start, &is_end);
+    let mut loop_edges = HashMap::<_, Vec<_>>::new();
+    for (i, walk) in walks.iter().enumerate() {
+      let mut node = walk.passes[walk.loop_pass];
+      for step in 0..walk.loop_len() {
+        let dir = self.route[step % self.route.len()];
+        let colours = loop_edges.entry((node, dir)).or_default();
+        if !colours.contains(&i) { colours.push(i); }
+        node = self.map[node][dir];
+      }
+    }
+
+    let mut dot = self.dot_nodes("network", &walks, &is_end);
+    for node in self.nodes() {
+      for (dir, &dest) in &self.map[node] {
+        let label = match dir { Dir::Left => "L", Dir::Right => "R" };
+        let attrs = loop_edges.get(&(node, dir)).map_or_else(String::new, |ghosts| format!(", {}", bold(ghosts)));
+        writeln!(dot, "  {:?} -> {:?} [label={}{}];", self.name(node), self.name(dest), label, attrs).unwrap();
+      }
+    }
+    dot.push_str("}\n");
+    dot
+  }
+
+  // The network collapsed to one edge per node, leading to where a whole pass through the route from there ends up.
+  // Edges are labelled with the steps into the pass that stand on ends. Nodes and ghosts are drawn as in network_dot,
+  // with each ghost's passes before it loops in its colour and those it keeps repeating in bold
+  pub fn jump_dot(&self, is_start: impl Fn(&str) -> bool, is_end: impl Fn(&str) -> bool) -> String {
+    let is_end = self.flags(is_end);
+    let walks = self.ghost_walks(is_start, &is_end);
+    let mut dot = self.dot_nodes("jumps", &walks, &is_end);
+    for node in self.nodes() {
+      let (dest, ends) = self.pass(node, &is_end);
+      let mut attrs = vec![];
+      if !ends.is_empty() {
+        attrs.push(format!("label=\"{}\"", ends.iter().join(", ")));
+      }
+      let ghosts: Vec<_> = walks.iter().positions(|walk| walk.passes.contains(&node)).collect();
+      if ghosts.iter().any(|&i| walks[i].passes[walks[i].loop_pass..].contains(&node)) {
+        attrs.push(bold(&ghosts));
+      } else if !ghosts.is_empty() {
+        attrs.push(format!("color=\"{}\"", colours(&ghosts)));
+      }
+      if attrs.is_empty() {
+        writeln!(dot, "  {:?} -> {:?};", self.name(node), self.name(dest)).unwrap();
+      } else {
+        writeln!(dot, "  {:?} -> {:?} [{}];", self.name(node), self.name(dest), attrs.join(", ")).unwrap();
+      }
+    }
+    dot.push_str("}\n");
+    dot
+  }
+
+  fn dot_nodes(&self, graph: &str, walks: &[Walk], is_end: &[bool]) -> String {
+    let mut dot = format!("digraph {} {{\n", graph);
+    for node in self.nodes() {
+      let mut attrs = vec![];
+      if let Some(i) = walks.iter().position(|walk| walk.passes[0] == node) {
+        let walk = &walks[i];
+        attrs.push("shape=box".to_owned());
+        attrs.push(format!("color={0}, fontcolor={0}", COLOURS[i % COLOURS.len()]));
+        let ends = walk.loop_ends.iter().map(|end| format!("+{}", end)).join(", ");
+        attrs.push(format!("xlabel=\"loops every {} steps from step {}; ends at {}\"", walk.loop_len(), walk.loop_start(), ends));
+      }
+      if is_end[node] {
+        attrs.push("peripheries=2".to_owned());
+      }
+      if attrs.is_empty() {
+        writeln!(dot, "  {:?};", self.name(node)).unwrap();
+      } else {
+        writeln!(dot, "  {:?} [{}];", self.name(node), attrs.join(", ")).unwrap();
+      }
+    }
+    dot
+  }
+
+  // One walk for each node that `is_start` accepts, in the order the map lists them
+  fn ghost_walks(&self, is_start: impl Fn(&str) -> bool, is_end: &[bool]) -> Vec<Walk> {
+    self.nodes()
+      .filter(|&node| is_start(self.name(node)))
+      .map(|start| self.walk_flagged(start, is_end))
+      .collect()
+  }
+
+  // Where one pass through the route from `start` ends up, and the steps into it that stand on ends
+  fn pass(&self, start: Node, is_end: &[bool]) -> (Node, Vec<usize>) {
+    let mut node = start;
+    let mut ends = vec![];
+    for (step, &dir) in self.route.iter().enumerate() {
+      if is_end[node] {
+        ends.push(step);
+      }
+      node = self.map[node][dir];
+    }
+    (node, ends)
   }
 
-  fn rotate_left(&mut self, shift: usize) {
-    let old_ends = take(&mut self.vals);
-    self.vals.extend(old_ends.into_iter()
-      .map(|end| (end + self.len - shift) % self.len));
+  fn flags(&self, pred: impl Fn(&str) -> bool) -> Vec<bool> {
+    self.names.iter().map(|name| pred(name)).collect()
   }
 
-  fn echo(&mut self, n: usize) {
-    let old_ends = take(&mut self.vals);
-    self.vals.extend(iproduct!(old_ends, 0..n)
-      .map(|(end, rep)| rep*self.len + end));
-    self.len *= n;
+  fn walk_flagged(&self, start: Node, is_end: &[bool]) -> Walk {
+    let pass_len = self.route.len();
+    let mut passes = vec![];
+    let mut seen = HashMap::new();
+    let mut ends = vec![];
+    let mut node = start;
+    loop {
+      if let Err((&mut loop_pass, _)) = seen.insert_vacant(node, passes.len()) {
+        let loop_ends = ends.split_off(ends.partition_point(|&end| end < loop_pass * pass_len))
+          .into_iter()
+          .map(|end| end - loop_pass * pass_len)
+          .collect();
+        break Walk { passes, loop_pass, pass_len, ends, loop_ends };
+      }
+      let (next, pass_ends) = self.pass(node, is_end);
+      ends.extend(pass_ends.into_iter().map(|end| passes.len() * pass_len + end));
+      passes.push(node);
+      node = next;
+    }
   }
 }
 
-#[derive(Clone)]
+// Ghosts beyond the last colour reuse them from the start
+const COLOURS: [&str; 6] = ["red", "blue", "darkgreen", "orange", "purple", "brown"];
+
+// A colour list, which Graphviz draws as parallel lines
+fn colours(ghosts: &[usize]) -> String {
+  ghosts.iter().map(|&i| COLOURS[i % COLOURS.len()]).join(":")
+}
+
+fn bold(ghosts: &[usize]) -> String {
+  format!("color=\"{}\", style=bold", colours(ghosts))
+}
+
+// A residue class of steps, (r, m) standing for every n with n ≡ r (mod m) and 0 <= r < m
+type Class = (BigInt, BigInt);
+
+// A set of steps made of the listed ones before `start`, then those in any of the classes from `start` on
+#[derive(Clone, Debug)]
 struct CyclicSet {
-  prefix: SparseRange,
-  cycle: SparseRange,
+  prefix: BTreeSet<usize>,
+  start: usize,
+  classes: BTreeSet<Class>,
 }
 
 impl CyclicSet {
+  fn new(walk: &Walk) -> Self {
+    let (start, len) = (walk.loop_start(), walk.loop_len());
+    // The ends may repeat more often than the walk does, and the shortest period keeps the classes few
+    let loop_ends: HashSet<_> = walk.loop_ends.iter().copied().collect();
+    let period = divisors(len)
+      .into_iter()
+      .find(|&d| loop_ends.iter().all(|&end| loop_ends.contains(&((end + d) % len))))
+      .unwrap();
+    let classes = loop_ends.iter()
+      .filter(|&&end| end < period)
+      .map(|&end| (BigInt::from((start + end) % period), BigInt::from(period)))
+      .collect();
+    Self { prefix: walk.ends.iter().copied().collect(), start, classes }
+  }
+
   fn contains(&self, end: usize) -> bool {
-    if end < self.prefix.len {
-      self.prefix.vals.contains(&end)
+    if end < self.start {
+      self.prefix.contains(&end)
     } else {
-      self.cycle.vals.contains(&((end - self.prefix.len) % self.cycle.len))
+      let end = BigInt::from(end);
+      self.classes.iter().any(|(r, m)| end.mod_floor(m) == *r)
     }
   }
 
-  fn intersection(mut self, mut other: Self) -> Self {
-    // Ensure self has the longer (or equal) prefix
-    if self.prefix.len < other.prefix.len {
-      swap(&mut self, &mut other);
-    }
-    // Intersect prefix with other whole set
-    self.prefix.vals.retain(|&end| other.contains(end));
-    // Rotate the other's cycle as if we had matched prefix lengths. From now on, other's prefix is invalid
-    other.cycle.rotate_left((self.prefix.len - other.prefix.len) % other.cycle.len);
-
-    // Expand the cycle length to the least common multiple of the two cycle lengths and fill duplicates to keep equivalence
-    self.cycle.echo(self.cycle.len.lcm(&other.cycle.len) / self.cycle.len);
-    // Intersect cycle with other cycle
-    self.cycle.vals.retain(|end| other.cycle.vals.contains(&(end % other.cycle.len)));
+  fn intersection(self, other: Self) -> Self {
+    let start = self.start.max(other.start);
+    // Below the later start, one set may already be cycling while the other is not
+    let prefix = self.prefix.iter().chain(&other.prefix)
+      .copied()
+      .chain(self.cycling_before(start))
+      .chain(other.cycling_before(start))
+      .filter(|&end| self.contains(end) && other.contains(end))
+      .collect();
+    let classes = iproduct!(&self.classes, &other.classes)
+      .filter_map(|(a, b)| crt(a, b))
+      .collect();
+    Self { prefix, start, classes }
+  }
 
-    self
+  // The steps from the set's own start up to `end` that its classes contain
+  fn cycling_before(&self, end: usize) -> impl Iterator<Item=usize> + '_ {
+    self.classes.iter()
+      .flat_map(move |class| {
+        let first = first_from(class, self.start);
+        let step = usize::try_from(&class.1).unwrap_or(usize::MAX);
+        iter::successors(usize::try_from(first).ok(), move |n| n.checked_add(step))
+          .take_while(move |&n| n < end)
+      })
   }
 
-  fn min(&self) -> Option<usize> {
-    self.prefix.vals.iter().next().copied()
-      .or(self.cycle.vals.iter().next().copied().map(|end| end + self.prefix.len))
+  fn min(&self) -> Option<BigUint> {
+    self.prefix.first().map(|&end| end.into())
+      .or_else(|| self.classes.iter().map(|class| first_from(class, self.start)).min().and_then(|n| n.to_biguint()))
   }
 }
 
-pub fn part2(file: String) -> usize {
-  let (route, map) = parse(&file).unwrap().1;
+// The first step from `from` on in the class
+fn first_from((r, m): &Class, from: usize) -> BigInt {
+  let from = BigInt::from(from);
+  (r - &from).mod_floor(m) + from
+}
 
-  let jumps: HashMap<_, _> = map.keys()
-    .map(|&jump_start| {
-      let mut loc = jump_start;
-      let mut ends = HashSet::new();
-      for steps in 0..route.len() {
-        if is_ghost_end(&loc) {
-          ends.insert(steps);
-        }
-        loc = map[&loc][route[steps]];
-      }
-      (jump_start, (loc, SparseRange { vals: ends, len: route.len() }))
-    })
-    .collect();
-
-  let sparse_range = |s: &[Location]|
-    s.iter().fold(SparseRange::default(), |mut ei, l| { ei.append(&jumps[l].1); ei });
-  let end_profiles: HashMap<Location, CyclicSet> = map.keys()
-    .copied()
-    .filter(is_ghost_start)
-    .map(|start| {
-      let mut loc = start;
-      let mut path = vec![];
-      let mut visited = HashMap::new();
-      loop {
-        if let Err(occupied) = visited.try_insert(loc, path.len()) {
-          let (prefix, cycle) = path.split_at(*occupied.entry.get());
-          break (start, CyclicSet { prefix: sparse_range(prefix), cycle: sparse_range(cycle) });
-        }
-        path.push(loc);
-        loc = jumps[&loc].0;
-      }
-    })
-    .collect();
+// The generalised Chinese remainder theorem: the steps in both classes form a class modulo the lcm of their moduli,
+// unless the residues disagree modulo the gcd, when there are none
+fn crt((r1, m1): &Class, (r2, m2): &Class) -> Option<Class> {
+  let ExtendedGcd { gcd, x, .. } = m1.extended_gcd(m2);
+  let (quot, rem) = (r2 - r1).div_rem(&gcd);
+  if !rem.is_zero() { return None; }
+  // m1·x ≡ gcd (mod m2), so stepping r1 on by m1·x·quot lands on r2 modulo m2
+  let lcm = m1 / &gcd * m2;
+  Some(((r1 + m1 * x * quot).mod_floor(&lcm), lcm))
+}
 
-  end_profiles.into_values()
-    .reduce(CyclicSet::intersection)
-    .and_then(|end_prof| end_prof.min())
-    .unwrap()
+// In increasing order
+fn divisors(n: usize) -> Vec<usize> {
+  let (mut small, mut large): (Vec<_>, Vec<_>) = (1..)
+    .take_while(|d| d * d <= n)
+    .filter(|d| n.is_multiple_of(*d))
+    .map(|d| (d, n / d))
+    .unzip();
+  large.reverse();
+  if small.last() == large.first() { large.remove(0); }
+  small.extend(large);
+  small
 }
 
-#[derive(Enum, Clone, Copy)]
+#[derive(Enum, Clone, Copy, PartialEq, Eq, Hash, Debug)]
 enum Dir {
   Left, Right,
 }
@@ -164,89 +403,247 @@ impl Dir {
   }
 }
 
-type Location = u32;
-type Route = Vec<Dir>;
-type Map = HashMap<Location, EnumMap<Dir, Location>>;
+fn parse_network(input: &str) -> error::Result<Network> {
+  let (route, lines) = parse_all(Solver::DAY, 0, input, parse)?;
+  // The map is listed one line per node after a blank line, so each line starts just after a newline
+  let line_start = |name: &str, nth: usize| input.match_indices(&format!("\n{} = ", name)).nth(nth).map_or(0, |(i, _)| i + 1);
+
+  let mut ids = HashMap::new();
+  for (i, &(name, _)) in lines.iter().enumerate() {
+    if ids.insert_vacant(name.to_owned(), i).is_err() {
+      return Err(Error::parse(Solver::DAY, 0, input, line_start(name, 1), format!("node {} is listed twice", name)));
+    }
+  }
+  // Every node the map leads to must itself be in the map, or the walks would fall off it
+  let map = lines.iter()
+    .map(|&(name, [left, right])| {
+      let id = |dest: &str| ids.get(dest).copied()
+        .ok_or_else(|| Error::parse(Solver::DAY, 0, input, line_start(name, 0), format!("unknown node {}", dest)));
+      let (left, right) = (id(left)?, id(right)?);
+      Ok(enum_map! { Dir::Left => left, Dir::Right => right })
+    })
+    .collect::<error::Result<_>>()?;
+  let names = lines.into_iter().map(|(name, _)| name.to_owned()).collect();
+  Ok(Network { route, names, ids, map })
+}
 
-fn parse(input: &str) -> IResult<&str, (Route, Map)> {
+type MapLine<'a> = (&'a str, [&'a str; 2]);
+
+fn parse(input: &str) -> IResult<&str, (Vec<Dir>, Vec<MapLine<'_>>)> {
   terminated(
     separated_pair(
       many1(Dir::parse),
       count(line_ending, 2),
-      map(
-        many1(parse_map_line),
-        |lines| lines.into_iter().collect(),
-      )
+      many1(parse_map_line),
     ),
     eof,
   )(input)
 }
 
-fn parse_location(input: &str) -> IResult<&str, Location> {
-  map(
-    count(
-      map(
-        one_of("ABCDEFGHIJKLMNOPQRSTUVWXYZ"),
-        |c| c as u32 - 'A' as u32,
-      ),
-      3,
-    ),
-    |cs| cs[0]*(26*26) + cs[1]*26 + cs[2],
-  )(input)
-}
-
-fn parse_map_line(input: &str) -> IResult<&str, (Location, EnumMap<Dir, Location>)> {
+fn parse_map_line(input: &str) -> IResult<&str, MapLine<'_>> {
   map(
     terminated(
       separated_pair(
-        parse_location,
+        alphanumeric1,
         tag(" = "),
         delimited(
           char('('),
           separated_pair(
-            parse_location,
+            alphanumeric1,
             tag(", "),
-            parse_location,
+            alphanumeric1,
           ),
           char(')'),
         ),
       ),
       line_ending,
     ),
-    |(start, (left, right))| (start, enum_map! { Dir::Left => left, Dir::Right => right })
+    |(name, (left, right))| (name, [left, right]),
   )(input)
 }
 
-fn is_ghost_start(loc: &Location) -> bool {
-  *loc % 26 == 0
-}
-fn is_ghost_end(loc: &Location) -> bool {
-  *loc % 26 == 25
-}
-
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_file;
+  use crate::{solution::Params as _, test_util::{sample_file, sample_input}};
+  use proptest::{prelude::*, collection::vec};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_file("08a")), 2);
-    assert_eq!(part1(sample_file("08b")), 6);
+    assert_eq!(part1(sample_input("08a")), Ok(2));
+    assert_eq!(part1(sample_input("08b")), Ok(6));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_file("08")), 19199);
+    assert_eq!(part1(sample_input("08")), Ok(19199));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_file("08c")), 6);
+    assert_eq!(part2(sample_input("08c")), Ok(6u32.into()));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_file("08")), 13663968099527);
+    assert_eq!(part2(sample_input("08")), Ok(13663968099527u64.into()));
+  }
+
+  #[test]
+  fn test_parse_error() {
+    let err = part1(sample_file("08a").replace("ZZZ = (ZZZ, ZZZ)", "ZZZ = (ZZZ ZZZ)").as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 8, line 9, column 1: unexpected input (End of file) at \"ZZZ = (ZZZ ZZZ)\"");
+    let err = part1(sample_file("08a").replace("GGG = (GGG, GGG)", "GGG = (GGG, YY)").as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 8, line 8, column 1: unknown node YY at \"GGG = (GGG, YY)\"");
+    let err = part1(sample_file("08a").replace("GGG = (GGG, GGG)", "DDD = (GGG, GGG)").as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 8, line 8, column 1: node DDD is listed twice at \"DDD = (GGG, GGG)\"");
+  }
+
+  #[test]
+  fn test_queries() {
+    let network = parse_network(&sample_file("08c")).unwrap();
+    let node = |name| network.node(name).unwrap();
+    assert_eq!(network.name(node("HHB")), "HHB");
+    assert_eq!(network.node("HH"), None);
+    assert_eq!(network.nodes().count(), 8);
+
+    let is_end = |name: &str| name.ends_with('Z');
+    assert_eq!(network.walk(node("GGA"), is_end), Walk {
+      passes: vec![node("GGA"), node("GGZ")], loop_pass: 1, pass_len: 2, ends: vec![], loop_ends: vec![0],
+    });
+    let walk = network.walk(node("HHA"), is_end);
+    assert_eq!((walk.loop_start(), walk.loop_len(), &walk.loop_ends), (2, 6, &vec![1, 4]));
+    assert_eq!(walk.first_end(), Some(3));
+    assert!((0..20).filter(|&n| walk.is_end(n)).eq([3, 6, 9, 12, 15, 18]));
+    assert_eq!(network.steps_to_end(node("HHA"), |name| name == "HHC"), Some(2));
+    assert_eq!(network.steps_to_end(node("GGA"), |name| name == "HHC"), None);
+    assert_eq!(network.ghost_steps(|name| name == "HHA", is_end), Ok(3u32.into()));
+  }
+
+  #[test]
+  fn test_dot() {
+    let network = parse_network(&sample_file("08c")).unwrap();
+    let (is_start, is_end) = (|name: &str| name.ends_with('A'), |name: &str| name.ends_with('Z'));
+    let dot = network.network_dot(is_start, is_end);
+    assert!(dot.starts_with("digraph network {\n"));
+    assert!(dot.contains("\n  \"GGZ\" [peripheries=2];\n"));
+    assert!(dot.contains("\n  \"GGA\" -> \"XXX\" [label=R];\n"));
+    assert!(dot.contains("\n  \"GGZ\" -> \"GGB\" [label=L, color=\"red\", style=bold];\n"));
+    assert!(dot.contains("\n  \"HHZ\" -> \"HHB\" [label=R, color=\"blue\", style=bold];\n"));
+
+    assert_eq!(network.jump_dot(is_start, is_end), r#"digraph jumps {
+  "GGA" [shape=box, color=red, fontcolor=red, xlabel="loops every 2 steps from step 2; ends at +0"];
+  "GGB";
+  "GGZ" [peripheries=2];
+  "HHA" [shape=box, color=blue, fontcolor=blue, xlabel="loops every 6 steps from step 2; ends at +1, +4"];
+  "HHB";
+  "HHC";
+  "HHZ" [peripheries=2];
+  "XXX";
+  "GGA" -> "GGZ" [color="red"];
+  "GGB" -> "XXX";
+  "GGZ" -> "GGZ" [label="0", color="red", style=bold];
+  "HHA" -> "HHC" [color="blue"];
+  "HHB" -> "HHZ" [color="blue", style=bold];
+  "HHC" -> "HHB" [label="1", color="blue", style=bold];
+  "HHZ" -> "HHC" [label="0", color="blue", style=bold];
+  "XXX" -> "XXX";
+}
+"#);
+
+    // Both ghosts loop through HHB's pass, so its edge carries both colours
+    let dot = network.jump_dot(|name| name == "HHA" || name == "HHB", is_end);
+    assert!(dot.contains("\n  \"HHB\" -> \"HHZ\" [color=\"red:blue\", style=bold];\n"));
+  }
+
+  #[test]
+  fn test_params() {
+    let input = "LR\n\nstart = (mid, start)\nmid = (start, goal)\ngoal = (goal, goal)\n";
+    let network = parse_network(input).unwrap();
+    let mut params = Params::default();
+    assert_eq!(Solver::part1(&network, &params).unwrap_err().to_string(), "no node AAA");
+    params.set("from", "start").unwrap();
+    params.set("to", "goal").unwrap();
+    assert_eq!(Solver::part1(&network, &params), Ok(Answer(2)));
+    params.set("to", "nowhere").unwrap();
+    assert_eq!(Solver::part1(&network, &params).unwrap_err().to_string(),
+      "nowhere is never reached from start");
+
+    params.set("ghost_start", "t").unwrap();
+    params.set("ghost_end", "l").unwrap();
+    assert_eq!(Solver::part2(&network, &params), Ok(Answer(2)));
+  }
+
+  #[test]
+  fn test_no_common_end() {
+    // One ghost ends on odd steps, the other on even ones
+    let input = "L\n\nBBA = (BBZ, BBZ)\nBBZ = (BBA, BBA)\nCCA = (CCB, CCB)\nCCB = (CCZ, CCZ)\nCCZ = (CCB, CCB)\n";
+    let err = part2(input.as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 8: the ghosts never all stand on ends at once");
+    let err = part2("L\n\nBBB = (BBB, BBB)\n".as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 8: no ghost starts");
+  }
+
+  #[test]
+  fn test_crt() {
+    let class = |r: i32, m: i32| (BigInt::from(r), BigInt::from(m));
+    assert_eq!(crt(&class(2, 3), &class(3, 5)), Some(class(8, 15)));
+    assert_eq!(crt(&class(1, 4), &class(3, 6)), Some(class(9, 12)));
+    assert_eq!(crt(&class(1, 4), &class(2, 6)), None);
+    assert_eq!(divisors(36), [1, 2, 3, 4, 6, 9, 12, 18, 36]);
+    assert_eq!(divisors(1), [1]);
+  }
+
+  // Small networks of starts, ends and plain nodes, with names of varying length. The first node is always a start
+  fn networks() -> impl Strategy<Value=Network> {
+    (vec(0..3usize, 1..7), vec(any::<bool>(), 1..5), vec((0..7usize, 0..7usize), 7))
+      .prop_map(|(mut kinds, turns, dests)| {
+        kinds[0] = 0;
+        let names: Vec<_> = kinds.iter().enumerate().map(|(i, &kind)| format!("{}{}", "N".repeat(i + 1), ["A", "B", "Z"][kind])).collect();
+        let route: String = turns.into_iter().map(|right| if right { 'R' } else { 'L' }).collect();
+        let lines: String = names.iter()
+          .zip(dests)
+          .map(|(name, (left, right))| format!("{} = ({}, {})\n", name, names[left % names.len()], names[right % names.len()]))
+          .collect();
+        parse_network(&format!("{}\n\n{}", route, lines)).unwrap()
+      })
+  }
+
+  // Reference walk: whether a ghost from `start` stands on an end after each of the first `steps` steps
+  fn brute_ends(network: &Network, start: Node, steps: usize) -> Vec<bool> {
+    let mut node = start;
+    (0..steps)
+      .map(|n| {
+        let end = network.name(node).ends_with('Z');
+        node = network.map[node][network.route[n % network.route.len()]];
+        end
+      })
+      .collect()
+  }
+
+  proptest! {
+    #[test]
+    fn test_walks_oracle(network in networks()) {
+      const STEPS: usize = 2000;
+      let is_end = |name: &str| name.ends_with('Z');
+      let walks: Vec<_> = network.nodes()
+        .filter(|&node| network.name(node).ends_with('A'))
+        .map(|start| (network.walk(start, is_end), brute_ends(&network, start, STEPS)))
+        .collect();
+      for (walk, brute) in &walks {
+        let prof = CyclicSet::new(walk);
+        prop_assert_eq!((0..STEPS).map(|n| walk.is_end(n)).collect::<Vec<_>>(), brute.clone());
+        prop_assert_eq!((0..STEPS).map(|n| prof.contains(n)).collect::<Vec<_>>(), brute.clone());
+        prop_assert_eq!(walk.first_end().filter(|&n| n < STEPS), brute.iter().position(|&end| end));
+        prop_assert_eq!(prof.min().filter(|n| *n < STEPS.into()), brute.iter().position(|&end| end).map(BigUint::from));
+      }
+
+      let common = walks.iter().map(|(walk, _)| CyclicSet::new(walk)).reduce(CyclicSet::intersection).unwrap();
+      let all_ends: Vec<_> = (0..STEPS).map(|n| walks.iter().all(|(_, brute)| brute[n])).collect();
+      prop_assert_eq!((0..STEPS).map(|n| common.contains(n)).collect::<Vec<_>>(), all_ends.clone());
+      let first_common = all_ends.iter().position(|&end| end);
+      prop_assert_eq!(network.ghost_steps(|name| name.ends_with('A'), is_end).ok().filter(|n| *n < STEPS.into()),
+        first_common.map(BigUint::from));
+    }
   }
 }
@@ -1,55 +1,146 @@
-use crate::util::isize;
-
-use itertools::Itertools;
-use nom::{IResult, character::complete::char, multi::separated_list1, sequence::terminated, combinator::eof};
-
-pub fn part1(lines: impl Iterator<Item=String>) -> isize {
-  lines
-    .map(|line| parse(&line).unwrap().1)
-    .map(|mut ns| {
-      let mut sum_lasts = 0;
-      loop {
-        sum_lasts += ns.last().copied().unwrap();
-        if ns.iter().all_equal() { break sum_lasts; }
-        ns = ns
-          .windows(2)
-          .map(|pair| pair[1] - pair[0])
-          .collect();
-      }
-    })
-    .sum()
+use std::io::BufRead;
+
+use crate::{solution::{self, read_input, joined_lines, parse_param, unknown_param, Solution, Answer}, error::{self, Error, Result}};
+
+use nom::{IResult, character::complete::{char, digit1}, multi::separated_list1, sequence::{terminated, pair}, combinator::{eof, opt, recognize, map}};
+use num::{BigInt, BigRational, One, Zero};
+
+pub fn part1(input: impl BufRead) -> Result<BigInt> { Ok(sum_extrapolated(&parse_lines(read_input(input)?.lines())?, 1)) }
+pub fn part2(input: impl BufRead) -> Result<BigInt> { Ok(sum_extrapolated(&parse_lines(read_input(input)?.lines())?, -1)) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<BigInt> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<BigInt> { part2(joined_lines(lines)) }
+// Each line's fit, for finding the sequences too short to confirm it
+pub fn fits(input: impl BufRead) -> Result<Vec<Fit>> { Ok(parse_lines(read_input(input)?.lines())?.iter().map(|seq| Fit::new(seq)).collect()) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 9;
+  type Input = Vec<Vec<BigInt>>;
+  type Params = Params;
+
+  fn parse(input: &str) -> Result<Self::Input> { parse_lines(input.lines()) }
+  fn part1(seqs: &Self::Input, params: &Params) -> Result<Answer> { sum_extrapolated(seqs, params.steps).try_into() }
+  fn part2(seqs: &Self::Input, params: &Params) -> Result<Answer> {
+    let steps = params.steps.checked_neg()
+      .ok_or_else(|| Error::Usage(format!("steps {} is too far to extrapolate back", params.steps)))?;
+    sum_extrapolated(seqs, steps).try_into()
+  }
+}
+
+// How far past the last value part 1 extrapolates, and before the first part 2 does
+pub struct Params {
+  pub steps: isize,
+}
+
+impl Default for Params {
+  fn default() -> Self { Self { steps: 1 } }
 }
 
-pub fn part2(lines: impl Iterator<Item=String>) -> isize {
-  lines
-    .map(|line| parse(&line).unwrap().1)
-    .map(|mut ns| {
-      let mut sum_firsts = 0;
-      loop {
-        sum_firsts += ns.first().copied().unwrap();
-        if ns.iter().all_equal() { break sum_firsts; }
-        ns = ns
-          .windows(2)
-          .map(|pair| pair[1] - pair[0])
-          .collect();
-
-        // Negate every other
-        sum_firsts -= ns.first().copied().unwrap();
-        if ns.iter().all_equal() { break sum_firsts; }
-        ns = ns
-          .windows(2)
-          .map(|pair| pair[1] - pair[0])
-          .collect();
+impl solution::Params for Params {
+  const NAMES: &'static [&'static str] = &["steps"];
+
+  fn set(&mut self, name: &str, value: &str) -> Result<()> {
+    match name {
+      "steps" => self.steps = parse_param(name, value)?,
+      _ => return unknown_param(name),
+    }
+    Ok(())
+  }
+}
+
+fn parse_lines(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Vec<Vec<BigInt>>> {
+  error::parse_lines(Solver::DAY, lines, parse)
+}
+
+// Steps forward from the last value if positive, or back from the first if negative. Sequences whose fit is not
+// confirmed are extrapolated all the same, as the polynomial of least degree through them
+fn sum_extrapolated(seqs: &[Vec<BigInt>], steps: isize) -> BigInt {
+  seqs.iter().map(|seq| Fit::new(seq).extrapolate(steps)).sum()
+}
+
+// The polynomial of least degree through a sequence, taking the values to be at 0, 1, 2 and so on. It is kept in
+// Newton's form, as the first value and its forward differences, so every value at a whole point stays a whole number
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Fit {
+  // Up to the last that is not zero, but always at least one
+  diffs: Vec<BigInt>,
+  len: usize,
+}
+
+impl Fit {
+  pub fn new(seq: &[BigInt]) -> Self {
+    let mut diffs = vec![];
+    let mut row = seq.to_vec();
+    while !row.is_empty() {
+      diffs.push(row[0].clone());
+      row = row.windows(2).map(|pair| &pair[1] - &pair[0]).collect();
+    }
+    while diffs.len() > 1 && diffs.last().is_some_and(Zero::is_zero) {
+      diffs.pop();
+    }
+    diffs.resize_with(diffs.len().max(1), BigInt::zero);
+    Self { diffs, len: seq.len() }
+  }
+
+  // A sequence of only zeros has degree 0
+  pub fn degree(&self) -> usize {
+    self.diffs.len() - 1
+  }
+
+  // Whether the sequence has a value to spare beyond those that pin down a polynomial of its degree. If not, any
+  // sequence would have fitted, so the fit says nothing about the values beyond it
+  pub fn is_confirmed(&self) -> bool {
+    self.degree() + 1 < self.len
+  }
+
+  pub fn at(&self, x: &BigInt) -> BigInt {
+    // Sum the differences times the binomials C(x, k), each got from the last by C(x, k+1) = C(x, k)·(x - k)/(k + 1)
+    let mut binom = BigInt::one();
+    let mut value = BigInt::zero();
+    for (k, diff) in self.diffs.iter().enumerate() {
+      value += diff * &binom;
+      binom = binom * (x - k) / (k + 1);
+    }
+    value
+  }
+
+  // Steps forward from the last value if positive, or back from the first if negative
+  pub fn extrapolate(&self, steps: isize) -> BigInt {
+    let x = if steps >= 0 { BigInt::from(self.len) - 1 + steps } else { BigInt::from(steps) };
+    self.at(&x)
+  }
+
+  // The polynomial's coefficients, lowest power first
+  pub fn coefficients(&self) -> Vec<BigRational> {
+    let mut coeffs = vec![BigRational::zero(); self.diffs.len()];
+    // The falling factorial x(x-1)...(x-k+1), lowest power first, and k!
+    let mut falling = vec![BigInt::one()];
+    let mut factorial = BigInt::one();
+    for (k, diff) in self.diffs.iter().enumerate() {
+      for (coeff, f) in coeffs.iter_mut().zip(&falling) {
+        *coeff += BigRational::new(diff * f, factorial.clone());
       }
-    })
-    .sum()
+      let mut next = vec![BigInt::zero(); falling.len() + 1];
+      for (i, f) in falling.iter().enumerate() {
+        next[i + 1] += f;
+        next[i] -= f * k;
+      }
+      falling = next;
+      factorial *= k + 1;
+    }
+    coeffs
+  }
 }
 
-fn parse(input: &str) -> IResult<&str, Vec<isize>> {
+fn parse(input: &str) -> IResult<&str, Vec<BigInt>> {
   terminated(
     separated_list1(
       char(' '),
-      isize,
+      map(
+        recognize(pair(opt(char('-')), digit1)),
+        |n: &str| n.parse().unwrap(),
+      ),
     ),
     eof,
   )(input)
@@ -58,25 +149,88 @@ fn parse(input: &str) -> IResult<&str, Vec<isize>> {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::{solution::Params as _, test_util::{sample_file, sample_input}};
+  use proptest::{prelude::*, collection::vec};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("09a")), 114);
+    assert_eq!(part1(sample_input("09a")), Ok(114.into()));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("09")), 1782868781);
+    assert_eq!(part1(sample_input("09")), Ok(1782868781.into()));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("09a")), 2);
+    assert_eq!(part2(sample_input("09a")), Ok(2.into()));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("09")), 1057);
+    assert_eq!(part2(sample_input("09")), Ok(1057.into()));
+  }
+
+  #[test]
+  fn test_fit() {
+    let seqs = parse_lines(sample_file("09a").lines()).unwrap();
+    let fits: Vec<_> = seqs.iter().map(|seq| Fit::new(seq)).collect();
+    assert_eq!(fits.iter().map(Fit::degree).collect::<Vec<_>>(), [1, 2, 3]);
+    assert!(fits.iter().all(Fit::is_confirmed));
+    let ratio = |n, d| BigRational::new(BigInt::from(n), BigInt::from(d));
+    // (x+1)(x+2)/2
+    assert_eq!(fits[1].coefficients(), [ratio(1, 1), ratio(3, 2), ratio(1, 2)]);
+    assert_eq!(fits[1].extrapolate(3), 45.into());
+    assert_eq!(fits[1].extrapolate(-3), 1.into());
+    assert_eq!(fits[0].extrapolate(-2), (-6).into());
+
+    assert_eq!(Fit::new(&[0.into(), 0.into()]).degree(), 0);
+    let powers: Vec<BigInt> = [1, 2, 4, 8, 16].map(BigInt::from).into();
+    assert_eq!(Fit::new(&powers).degree(), 4);
+    assert!(!Fit::new(&powers).is_confirmed());
+    assert!(!Fit::new(&[5.into()]).is_confirmed());
+  }
+
+  #[test]
+  fn test_extrapolate() {
+    // Differencing the first in isize would overflow, as would its next value
+    let seqs = parse_lines(["-9223372036854775807 0 9223372036854775807", "1 2 4 8"].into_iter()).unwrap();
+    assert_eq!(Solver::part1(&seqs[..1].to_vec(), &Params::default()), Ok(Answer(18446744073709551614)));
+    // The cubic through 1 2 4 8 goes on to 15
+    assert_eq!(Solver::part1(&seqs, &Params::default()), Ok(Answer(18446744073709551614 + 15)));
+    let fits = fits("1 2 4 8\n5\n1 2 3\n".as_bytes()).unwrap();
+    assert_eq!(fits.iter().map(Fit::is_confirmed).collect::<Vec<_>>(), [false, false, true]);
+    // A single value is taken to be constant
+    assert_eq!(part1("5\n".as_bytes()), Ok(5.into()));
+    assert_eq!(part2("5\n".as_bytes()), Ok(5.into()));
+    assert_eq!(Fit::new(&[]).extrapolate(1), 0.into());
+
+    let mut params = Params::default();
+    params.set("steps", "3").unwrap();
+    let seqs = parse_lines(sample_file("09a").lines()).unwrap();
+    assert_eq!(Solver::part1(&seqs, &params), Ok(Answer(24 + 45 + 146)));
+    assert_eq!(Solver::part2(&seqs, &params), Ok(Answer(-9 + 1 - 19)));
+    params.set("steps", &isize::MIN.to_string()).unwrap();
+    let err = Solver::part2(&seqs, &params).unwrap_err();
+    assert_eq!(err.to_string(), format!("steps {} is too far to extrapolate back", isize::MIN));
+  }
+
+  proptest! {
+    #[test]
+    fn test_fit_oracle(coeffs in vec(-50..50i64, 1..6), extra in 1..5usize) {
+      let poly = |x: i64| coeffs.iter().rev().fold(BigInt::zero(), |acc, &c| acc * x + c);
+      let degree = coeffs.iter().rposition(|&c| c != 0).unwrap_or(0);
+      let len = coeffs.len() + extra;
+      let seq: Vec<_> = (0..len as i64).map(poly).collect();
+      let fit = Fit::new(&seq);
+      prop_assert_eq!(fit.degree(), degree);
+      prop_assert!(fit.is_confirmed());
+      for x in -20..40 {
+        prop_assert_eq!(fit.at(&x.into()), poly(x));
+      }
+      let expected: Vec<_> = coeffs[..=degree].iter().map(|&c| BigRational::from(BigInt::from(c))).collect();
+      prop_assert_eq!(fit.coefficients(), expected);
+    }
   }
 }
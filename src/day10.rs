@@ -1,141 +1,172 @@
-use std::mem::swap;
+use std::{collections::BTreeSet, mem::swap, io::BufRead};
 
-use bitvec::prelude::*;
 use itertools::{Itertools, iproduct};
-use strum::{EnumIter, IntoEnumIterator};
+use strum::IntoEnumIterator;
 
-use crate::util::Coord;
+use crate::{util::{Coord, Dir, Grid}, solution::{read_input, joined_lines, Solution, Answer}, error::{Error, Result}};
 
-pub fn part1(lines: impl Iterator<Item=String>) -> usize {
-  let (map, map_size, start) = prep(lines);
+pub fn part1(input: impl BufRead) -> Result<usize> { Ok(loop_len(&prep(read_input(input)?.lines())?)) }
+pub fn part2(input: impl BufRead) -> Result<usize> { Ok(enclosed_count(&prep(read_input(input)?.lines())?)) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part2(joined_lines(lines)) }
 
-  let mut dir = map[start.0][start.1].connects()[0];
-  let mut path_len = 0;
-  let mut pos = start;
-  loop {
-    pos = dir.try_step(pos, map_size).unwrap();
-    path_len += 1;
-    if pos == start { break; }
-    dir = dir.try_turn(map[pos.0][pos.1]).unwrap();
-  }
+pub struct Solver;
 
-  path_len / 2
+impl Solution for Solver {
+  const DAY: u8 = 10;
+  type Input = Maze;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> { prep(input.lines()) }
+  fn part1(maze: &Self::Input, _: &()) -> Result<Answer> { Ok(loop_len(maze).into()) }
+  fn part2(maze: &Self::Input, _: &()) -> Result<Answer> { Ok(enclosed_count(maze).into()) }
 }
 
-pub fn part2(lines: impl Iterator<Item=String>) -> usize {
-  let (map, map_size, _) = prep(lines);
+pub struct Maze {
+  map: Grid<Tile>,
+  start: Coord,
+  tiles: Vec<Coord>,
+}
 
-  let mut interpoints = vec![bitvec![0; map[0].len() + 1]; map.len() + 1];
-  let interpoints_size = (map_size.0 + 1, map_size.1 + 1);
-  interpoints[0].set(0, true);
-  let mut to_visit = vec![(0, 0)];
+impl Maze {
+  pub fn start(&self) -> Coord {
+    self.start
+  }
+
+  // The pipe under the start tile, inferred from the pipes around it
+  pub fn start_tile(&self) -> Tile {
+    self.map[self.start]
+  }
+
+  // The tiles of the loop in order, from the start round in whichever direction its pipe lists first
+  pub fn loop_tiles(&self) -> &[Coord] {
+    &self.tiles
+  }
+
+  // The tiles enclosed by the loop, found by flooding the corners between tiles from outside. Pipes off the loop do not
+  // block the flood, so they are cleared first
+  pub fn enclosed(&self) -> BTreeSet<Coord> {
+    let mut map = Grid::new(self.map.size(), Tile::Empty);
+    for &pos in self.loop_tiles() {
+      map[pos] = self.map[pos];
+    }
+    let map = &map;
+    let mut interpoints = Grid::new((map.height() + 1, map.width() + 1), false);
+    interpoints[(0, 0)] = true;
+    let mut to_visit = vec![(0, 0)];
 
-  while let Some(pos) = to_visit.pop() {
-    for dir in Dir::iter() {
-      if let Some(nbr) = dir.interpoints_try_step(pos, interpoints_size, &map) {
-        if !interpoints[nbr.0][nbr.1] {
-          interpoints[nbr.0].set(nbr.1, true);
-          to_visit.push(nbr);
+    while let Some(pos) = to_visit.pop() {
+      for dir in Dir::iter() {
+        if let Some(nbr) = interpoints_try_step(pos, dir, map) {
+          if !interpoints[nbr] {
+            interpoints[nbr] = true;
+            to_visit.push(nbr);
+          }
         }
       }
     }
+
+    map.positions()
+      .filter(|&(y, x)| iproduct!(y..=y+1, x..=x+1)
+        .all(|ipos| !interpoints[ipos]))
+      .collect()
+  }
+
+  // How many tiles the loop encloses, by another route than enclosed(). The shoelace formula gives the area of the
+  // polygon through the loop tiles' centres, and Pick's theorem then counts the whole points strictly inside it
+  pub fn enclosed_area(&self) -> usize {
+    let tiles = self.loop_tiles();
+    let twice_area = tiles.iter()
+      .circular_tuple_windows()
+      .map(|(&(y0, x0), &(y1, x1))| x0 as isize * y1 as isize - x1 as isize * y0 as isize)
+      .sum::<isize>()
+      .unsigned_abs();
+    // A = i + b/2 - 1, with every loop tile a boundary point
+    (twice_area + 2 - tiles.len()) / 2
   }
+}
 
-  iproduct!(0..map_size.0, 0..map_size.1)
-    .filter(|&(y, x)| iproduct!(y..=y+1, x..=x+1)
-      .all(|(iy, ix)| !interpoints[iy][ix]))
-    .count()
+fn loop_len(maze: &Maze) -> usize {
+  maze.loop_tiles().len() / 2
 }
 
-fn prep(lines: impl Iterator<Item=String>) -> (Vec<Vec<Tile>>, Coord, Coord) {
-  let mut map: Vec<Vec<_>> = lines
-    .map(|s| s.chars().map(Tile::try_parse).map(Option::unwrap).collect())
+fn enclosed_count(maze: &Maze) -> usize {
+  maze.enclosed().len()
+}
+
+fn prep(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Maze> {
+  let mut map = Grid::parse(Solver::DAY, lines, Tile::try_parse)?;
+  let starts: Vec<_> = map.iter()
+    .filter(|&(_, &tile)| tile == Tile::Start)
+    .map(|(pos, _)| pos)
+    .take(2)
     .collect();
-  let map_size = (map.len(), map[0].len());
-
-  let start = map.iter()
-    .enumerate()
-    .flat_map(|(y, row)| row.iter()
-      .enumerate()
-      .filter(|(_, &c)| c == Tile::Start)
-      .map(move |(x, _)| (y, x)))
-    .exactly_one().unwrap();
+  let start = match starts[..] {
+    [] => return Err(Error::input(Solver::DAY, "no start tile")),
+    [start] => start,
+    [_, other, ..] => return Err(Error::at_tile(Solver::DAY, other, "second start tile")),
+  };
+
   let dirs: Vec<_> = Dir::iter()
-    .filter(|d|
-      d.try_step(start, map_size)
-        .and_then(|(y, x)| d.try_turn(map[y][x]))
+    .filter(|&d|
+      map.step(start, d)
+        .and_then(|nbr| map[nbr].turn(d))
         .is_some()
     )
     .collect();
-  assert_eq!(dirs.len(), 2);
-  map[start.0][start.1] = Tile::connecting(dirs[0], dirs[1]);
+  let start_tile = match dirs[..] {
+    [d0, d1] => Tile::connecting(d0, d1),
+    _ => None,
+  };
+  map[start] = start_tile
+    .ok_or_else(|| Error::at_tile(Solver::DAY, start, format!("start tile connects to {} pipes, not 2", dirs.len())))?;
 
-  (map, map_size, start)
-}
-
-#[derive(EnumIter, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
-enum Dir { E, N, W, S }
-
-impl Dir {
-  fn try_step(&self, (y, x): Coord, (max_y, max_x): Coord) -> Option<Coord> {
-    match self {
-      Self::E => x.checked_add(1).map(|x| (y, x)).filter(|&(_, x)| x < max_x),
-      Self::N => y.checked_sub(1).map(|y| (y, x)),
-      Self::W => x.checked_sub(1).map(|x| (y, x)),
-      Self::S => y.checked_add(1).map(|y| (y, x)).filter(|&(y, _)| y < max_y),
-    }
+  // Follow the loop round, checking that each pipe leads on to one that connects back
+  let mut tiles = vec![start];
+  let mut dir = dirs[0];
+  let mut pos = start;
+  loop {
+    pos = map.step(pos, dir).ok_or_else(|| Error::at_tile(Solver::DAY, pos, "pipe leads off the map"))?;
+    if pos == start { break; }
+    tiles.push(pos);
+    dir = map[pos].turn(dir).ok_or_else(|| Error::at_tile(Solver::DAY, pos, "pipe does not connect to the one before it"))?;
   }
 
-  fn interpoints_try_step(&self, (y, x): Coord, interpoints_size: Coord, map: &Vec<Vec<Tile>>) -> Option<Coord> {
-    let dest = self.try_step((y, x), interpoints_size)?;
-    let map_size = (interpoints_size.0 - 1, interpoints_size.1 - 1);
-    let valid_tile = |&(y, x): &_| y < map_size.0 && x < map_size.1;
-    let between_tiles = || {
-      let y_m1 = y.checked_sub(1);
-      let x_m1 = x.checked_sub(1);
-      let se = Some((y, x)).filter(valid_tile);
-      let ne = y_m1.map(|y| (y, x)).filter(valid_tile);
-      let sw = x_m1.map(|x| (y, x)).filter(valid_tile);
-      let nw = y_m1.and_then(|y| x_m1.map(|x| (y, x))).filter(valid_tile);
-      Some(match self {
-        Self::E => (ne?, Self::S, se?, Self::N),
-        Self::N => (nw?, Self::E, ne?, Self::W),
-        Self::W => (sw?, Self::N, nw?, Self::S),
-        Self::S => (se?, Self::W, sw?, Self::E),
-      })
-    };
-    if let Some((t0, d0, t1, d1)) = between_tiles() {
-      if map[t0.0][t0.1].connects().contains(&d0) && map[t1.0][t1.1].connects().contains(&d1) {
-        None
-      } else {
-        Some(dest)
-      }
+  Ok(Maze { map, start, tiles })
+}
+
+// Interpoints are the corners between tiles, so there is one more of them than tiles in each direction.
+// Moving between two interpoints is blocked by a pipe connecting the tiles on either side
+fn interpoints_try_step((y, x): Coord, dir: Dir, map: &Grid<Tile>) -> Option<Coord> {
+  let dest = dir.try_step((y, x), (map.height() + 1, map.width() + 1))?;
+  let valid_tile = |&pos: &Coord| map.get(pos).is_some();
+  let between_tiles = || {
+    let y_m1 = y.checked_sub(1);
+    let x_m1 = x.checked_sub(1);
+    let se = Some((y, x)).filter(valid_tile);
+    let ne = y_m1.map(|y| (y, x)).filter(valid_tile);
+    let sw = x_m1.map(|x| (y, x)).filter(valid_tile);
+    let nw = y_m1.and_then(|y| x_m1.map(|x| (y, x))).filter(valid_tile);
+    Some(match dir {
+      Dir::E => (ne?, Dir::S, se?, Dir::N),
+      Dir::N => (nw?, Dir::E, ne?, Dir::W),
+      Dir::W => (sw?, Dir::N, nw?, Dir::S),
+      Dir::S => (se?, Dir::W, sw?, Dir::E),
+    })
+  };
+  if let Some((t0, d0, t1, d1)) = between_tiles() {
+    if map[t0].connects().contains(&d0) && map[t1].connects().contains(&d1) {
+      None
     } else {
       Some(dest)
     }
-  }
-
-  fn try_turn(&self, t: Tile) -> Option<Self> {
-    match (self, t) {
-      (Self::E, Tile::PipeEW) => Some(Self::E),
-      (Self::E, Tile::PipeNW) => Some(Self::N),
-      (Self::E, Tile::PipeWS) => Some(Self::S),
-      (Self::N, Tile::PipeNS) => Some(Self::N),
-      (Self::N, Tile::PipeSE) => Some(Self::E),
-      (Self::N, Tile::PipeWS) => Some(Self::W),
-      (Self::W, Tile::PipeEW) => Some(Self::W),
-      (Self::W, Tile::PipeEN) => Some(Self::N),
-      (Self::W, Tile::PipeSE) => Some(Self::S),
-      (Self::S, Tile::PipeNS) => Some(Self::S),
-      (Self::S, Tile::PipeEN) => Some(Self::E),
-      (Self::S, Tile::PipeNW) => Some(Self::W),
-      _ => None,
-    }
+  } else {
+    Some(dest)
   }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
-enum Tile { Empty, Start, PipeEW, PipeNS, PipeEN, PipeNW, PipeWS, PipeSE }
+pub enum Tile { Empty, Start, PipeEW, PipeNS, PipeEN, PipeNW, PipeWS, PipeSE }
 
 impl Tile {
   fn try_parse(c: char) -> Option<Self> {
@@ -152,25 +183,45 @@ impl Tile {
     }
   }
 
-  fn connecting(mut d0: Dir, mut d1: Dir) -> Self {
+  // None if the directions are the same
+  pub fn connecting(mut d0: Dir, mut d1: Dir) -> Option<Self> {
     if d0 > d1 {
       swap(&mut d0, &mut d1);
     }
     match (d0, d1) {
-      (Dir::N, Dir::S) => Self::PipeNS,
-      (Dir::E, Dir::W) => Self::PipeEW,
-      (Dir::E, Dir::N) => Self::PipeEN,
-      (Dir::N, Dir::W) => Self::PipeNW,
-      (Dir::W, Dir::S) => Self::PipeWS,
-      (Dir::E, Dir::S) => Self::PipeSE,
-      _ => panic!(),
+      (Dir::N, Dir::S) => Some(Self::PipeNS),
+      (Dir::E, Dir::W) => Some(Self::PipeEW),
+      (Dir::E, Dir::N) => Some(Self::PipeEN),
+      (Dir::N, Dir::W) => Some(Self::PipeNW),
+      (Dir::W, Dir::S) => Some(Self::PipeWS),
+      (Dir::E, Dir::S) => Some(Self::PipeSE),
+      _ => None,
     }
   }
 
-  fn connects(&self) -> &'static [Dir] {
+  // The direction to continue in after entering this tile moving in `dir`
+  pub fn turn(&self, dir: Dir) -> Option<Dir> {
+    match (dir, self) {
+      (Dir::E, Self::PipeEW) => Some(Dir::E),
+      (Dir::E, Self::PipeNW) => Some(Dir::N),
+      (Dir::E, Self::PipeWS) => Some(Dir::S),
+      (Dir::N, Self::PipeNS) => Some(Dir::N),
+      (Dir::N, Self::PipeSE) => Some(Dir::E),
+      (Dir::N, Self::PipeWS) => Some(Dir::W),
+      (Dir::W, Self::PipeEW) => Some(Dir::W),
+      (Dir::W, Self::PipeEN) => Some(Dir::N),
+      (Dir::W, Self::PipeSE) => Some(Dir::S),
+      (Dir::S, Self::PipeNS) => Some(Dir::S),
+      (Dir::S, Self::PipeEN) => Some(Dir::E),
+      (Dir::S, Self::PipeNW) => Some(Dir::W),
+      _ => None,
+    }
+  }
+
+  pub fn connects(&self) -> &'static [Dir] {
     match self {
-      Self::Empty => &[],
-      Self::Start => panic!(),
+      // The pipe under the start is unknown until prep infers it
+      Self::Empty | Self::Start => &[],
       Self::PipeEW => &[Dir::E, Dir::W],
       Self::PipeNS => &[Dir::N, Dir::S],
       Self::PipeEN => &[Dir::E, Dir::N],
@@ -184,29 +235,74 @@ impl Tile {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::test_util::{sample_file, sample_input};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("10a")), 4);
-    assert_eq!(part1(sample_lines("10b")), 8);
+    assert_eq!(part1(sample_input("10a")), Ok(4));
+    assert_eq!(part1(sample_input("10b")), Ok(8));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("10")), 6778);
+    assert_eq!(part1(sample_input("10")), Ok(6778));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("10c")), 4);
-    assert_eq!(part2(sample_lines("10d")), 4);
-    assert_eq!(part2(sample_lines("10e")), 8);
-    assert_eq!(part2(sample_lines("10f")), 10);
+    assert_eq!(part2(sample_input("10c")), Ok(4));
+    assert_eq!(part2(sample_input("10d")), Ok(4));
+    assert_eq!(part2(sample_input("10e")), Ok(8));
+    assert_eq!(part2(sample_input("10f")), Ok(10));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("10")), 433);
+    assert_eq!(part2(sample_input("10")), Ok(433));
+  }
+
+  #[test]
+  fn test_parse_error() {
+    let err = part1(sample_file("10a").replace('-', "X").as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 10, line 2, column 3: unexpected tile at \"X7.\"");
+    let err = part1(sample_file("10a").replace('S', ".").as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 10: no start tile");
+    let err = part1(sample_file("10a").replace('J', "S").as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 10, line 4, column 4: second start tile");
+    let err = part1(sample_file("10a").replace(".|.|.", "...|.").as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 10, line 2, column 2: start tile connects to 1 pipes, not 2");
+    let err = part1(".....\n.S-7.\n.|.|.\n.L-|.\n.....\n".as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 10, line 5, column 4: pipe does not connect to the one before it");
+    let err = part1("S-\n|.\n".as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 10, line 1, column 2: pipe leads off the map");
+  }
+
+  #[test]
+  fn test_queries() {
+    let maze = prep(sample_file("10b").lines()).unwrap();
+    assert_eq!(maze.start(), (2, 0));
+    assert_eq!(maze.start_tile(), Tile::PipeSE);
+    let tiles = maze.loop_tiles();
+    assert_eq!(tiles.len(), 16);
+    assert_eq!(tiles[..4], [(2, 0), (2, 1), (1, 1), (1, 2)]);
+    // Each tile leads on to the next, and the last back to the first
+    assert!(tiles.iter().circular_tuple_windows().all(|(&(y0, x0), &(y1, x1))| y0.abs_diff(y1) + x0.abs_diff(x1) == 1));
+    assert_eq!(maze.enclosed(), BTreeSet::from([(2, 2)]));
+
+    let maze = prep(sample_file("10c").lines()).unwrap();
+    assert_eq!(maze.start_tile(), Tile::PipeSE);
+    assert_eq!(maze.enclosed(), BTreeSet::from([(6, 2), (6, 3), (6, 7), (6, 8)]));
+  }
+
+  #[test]
+  fn test_enclosed_area() {
+    for id in ["10a", "10b", "10c", "10d", "10e", "10f", "10"] {
+      let maze = prep(sample_file(id).lines()).unwrap();
+      assert_eq!(maze.enclosed_area(), maze.enclosed().len(), "{}", id);
+    }
+    // The pipes between the two halves of the row are not on the loop
+    let maze = prep("S-7.F-7\n|.|.|.|\nL-J.L-J\n".lines()).unwrap();
+    assert_eq!(enclosed_count(&maze), 1);
+    assert_eq!(maze.enclosed_area(), 1);
   }
 }
@@ -1,22 +1,41 @@
-use std::{collections::{BTreeSet, HashMap}, cmp::{min, max}};
+use std::{collections::{BTreeSet, HashMap}, cmp::{min, max}, io::BufRead};
 
 use itertools::Itertools;
 
-use crate::util::Coord;
+use crate::{util::{Coord, Grid}, solution::{read_input, joined_lines, Solution, Answer}, error::Result};
 
-pub fn part1(lines: impl Iterator<Item=String>) -> usize { process(lines, 2) }
-pub fn part2(lines: impl Iterator<Item=String>) -> usize { process(lines, 1_000_000) }
+pub fn part1(input: impl BufRead) -> Result<usize> { process(read_input(input)?.lines(), 2) }
+pub fn part2(input: impl BufRead) -> Result<usize> { process(read_input(input)?.lines(), 1_000_000) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part2(joined_lines(lines)) }
 
-fn process(lines: impl Iterator<Item=String>, gap_mult: usize) -> usize {
-  let mut galaxies: Vec<_> = lines
-    .enumerate()
-    .flat_map(|(y, line)| line.chars()
-      .enumerate()
-      .filter(|&(_, c)| c == '#')
-      .map(|(x, _)| (y, x))
-      .collect::<Vec<_>>())
-    .collect();
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 11;
+  type Input = Vec<Coord>;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> { parse(input.lines()) }
+  fn part1(galaxies: &Self::Input, _: &()) -> Result<Answer> { Ok(sum_dists(galaxies.clone(), 2).into()) }
+  fn part2(galaxies: &Self::Input, _: &()) -> Result<Answer> { Ok(sum_dists(galaxies.clone(), 1_000_000).into()) }
+}
+
+fn process(lines: impl Iterator<Item=impl AsRef<str>>, gap_mult: usize) -> Result<usize> {
+  Ok(sum_dists(parse(lines)?, gap_mult))
+}
 
+// The galaxies in reading order
+fn parse(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Vec<Coord>> {
+  let image = Grid::parse(Solver::DAY, lines, |c| match c {
+    '#' => Some(true),
+    '.' => Some(false),
+    _ => None,
+  })?;
+  Ok(image.iter().filter(|&(_, &galaxy)| galaxy).map(|(pos, _)| pos).collect())
+}
+
+fn sum_dists(mut galaxies: Vec<Coord>, gap_mult: usize) -> usize {
   // galaxies is sorted in ascending y order
   let ys = multiply_gaps(
     galaxies.iter().map(|&(y, _)| y).dedup(),
@@ -49,26 +68,62 @@ fn multiply_gaps(sorted_ns: impl Iterator<Item=usize>, gap_mult: usize) -> HashM
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::test_util::{sample_file, sample_input};
+  use proptest::{prelude::*, collection::btree_set};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("11a")), 374);
+    assert_eq!(part1(sample_input("11a")), Ok(374));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("11")), 9605127);
+    assert_eq!(part1(sample_input("11")), Ok(9605127));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(process(sample_lines("11a"), 10), 1030);
-    assert_eq!(process(sample_lines("11a"), 100), 8410);
+    assert_eq!(process(sample_file("11a").lines(), 10), Ok(1030));
+    assert_eq!(process(sample_file("11a").lines(), 100), Ok(8410));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("11")), 458191688761);
+    assert_eq!(part2(sample_input("11")), Ok(458191688761));
+  }
+
+  // Reference expansion: literally repeat each empty row and column of the image, then measure
+  fn brute_sum_dists(galaxies: &BTreeSet<Coord>, gap_mult: usize) -> usize {
+    let height = galaxies.iter().map(|&(y, _)| y + 1).max().unwrap_or(0);
+    let width = galaxies.iter().map(|&(_, x)| x + 1).max().unwrap_or(0);
+    let rows: Vec<Vec<bool>> = (0..height)
+      .flat_map(|y| {
+        let row: Vec<_> = (0..width).map(|x| galaxies.contains(&(y, x))).collect();
+        let copies = if row.contains(&true) { 1 } else { gap_mult };
+        vec![row; copies]
+      })
+      .collect();
+    let empty_cols: Vec<_> = (0..width).map(|x| !rows.iter().any(|row| row[x])).collect();
+    let expanded: Vec<Vec<bool>> = rows.iter()
+      .map(|row| row.iter()
+        .zip(&empty_cols)
+        .flat_map(|(&cell, &empty)| vec![cell; if empty { gap_mult } else { 1 }])
+        .collect())
+      .collect();
+    let positions: Vec<_> = expanded.iter()
+      .enumerate()
+      .flat_map(|(y, row)| row.iter().positions(|&cell| cell).map(move |x| (y, x)))
+      .collect();
+    positions.iter()
+      .tuple_combinations()
+      .map(|(&a, &b)| dist(a, b))
+      .sum()
+  }
+
+  proptest! {
+    #[test]
+    fn test_sum_dists_oracle(galaxies in btree_set((0..12usize, 0..12usize), 0..12), gap_mult in 1..5usize) {
+      prop_assert_eq!(sum_dists(galaxies.iter().copied().collect(), gap_mult), brute_sum_dists(&galaxies, gap_mult));
+    }
   }
 }
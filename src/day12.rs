@@ -1,38 +1,54 @@
-use std::{cmp::min, collections::HashMap};
+use std::{cmp::min, collections::HashMap, io::BufRead};
 
-use crate::util::usize;
+use crate::{util::usize, solution::{read_input, joined_lines, Solution, Answer}, error::{self, Result}};
 
 use nom::{IResult, character::complete::{char, one_of}, multi::{separated_list1, many1}, sequence::{terminated, separated_pair}, combinator::{eof, map}};
 
-pub fn part1(lines: impl Iterator<Item=String>) -> usize {
-  let puzzles: Vec<_> = lines
-    .map(|line| parse(&line).unwrap().1)
-    .collect();
+pub fn part1(input: impl BufRead) -> Result<usize> { Ok(sum_solutions(&parse_lines(read_input(input)?.lines())?)) }
+pub fn part2(input: impl BufRead) -> Result<usize> { Ok(sum_solutions(&unfold(&parse_lines(read_input(input)?.lines())?))) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part2(joined_lines(lines)) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 12;
+  type Input = Vec<Puzzle>;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> { parse_lines(input.lines()) }
+  fn part1(puzzles: &Self::Input, _: &()) -> Result<Answer> { Ok(sum_solutions(puzzles).into()) }
+  fn part2(puzzles: &Self::Input, _: &()) -> Result<Answer> { Ok(sum_solutions(&unfold(puzzles)).into()) }
+}
+
+type Puzzle = (Vec<Option<bool>>, Vec<usize>);
+
+fn parse_lines(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Vec<Puzzle>> {
+  error::parse_lines(Solver::DAY, lines, parse)
+}
+
+fn sum_solutions(puzzles: &[Puzzle]) -> usize {
   let mut memo: Memo = Default::default();
   puzzles.iter()
-    .map(|(pat, runs)| count_solutions_memo(&pat, &runs, &mut memo))
+    .map(|(pat, runs)| count_solutions_memo(pat, runs, &mut memo))
     .sum()
 }
-pub fn part2(lines: impl Iterator<Item=String>) -> usize {
-  let puzzles: Vec<_> = lines
-    .map(|line| parse(&line).unwrap().1)
+
+fn unfold(puzzles: &[Puzzle]) -> Vec<Puzzle> {
+  puzzles.iter()
     .map(|(pat, runs)| {
       let mut big_pat = Vec::with_capacity(pat.len() * 5 + 4);
-      big_pat.extend(&pat);
+      big_pat.extend(pat);
       let mut big_runs = Vec::with_capacity(runs.len() * 5);
-      big_runs.extend(&runs);
+      big_runs.extend(runs);
       for _ in 0..4 {
         big_pat.push(None);
-        big_pat.extend(&pat);
-        big_runs.extend(&runs);
+        big_pat.extend(pat);
+        big_runs.extend(runs);
       }
       (big_pat, big_runs)
     })
-    .collect();
-  let mut memo: Memo = Default::default();
-  puzzles.iter()
-    .map(|(pat, runs)| count_solutions_memo(&pat, &runs, &mut memo))
-    .sum()
+    .collect()
 }
 
 type Memo<'a> = HashMap<(&'a [Option<bool>], &'a [usize]), usize>;
@@ -108,7 +124,7 @@ fn count_solutions<'a>(mut pat: &'a [Option<bool>], mut runs: &'a [usize], memo:
 fn could_start_run(pat: &[Option<bool>], runs: &[usize]) -> bool {
   if let Some(&rem_range) = runs.first() {
     rem_range <= pat.len()
-    && !pat[1..rem_range].iter().any(|&sym| sym == Some(false))
+    && !pat[1..rem_range].contains(&Some(false))
 
     // Must be followed by an empty or a ? (presumed empty) or the end of the pattern
     && pat.get(rem_range).copied() != Some(Some(true))
@@ -130,7 +146,7 @@ fn could_start_run(pat: &[Option<bool>], runs: &[usize]) -> bool {
   }
 }*/
 
-fn parse(input: &str) -> IResult<&str, (Vec<Option<bool>>, Vec<usize>)> {
+fn parse(input: &str) -> IResult<&str, Puzzle> {
   terminated(
     separated_pair(
       many1(map(
@@ -147,25 +163,53 @@ fn parse(input: &str) -> IResult<&str, (Vec<Option<bool>>, Vec<usize>)> {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::test_util::sample_input;
+  use itertools::Itertools;
+  use proptest::{prelude::*, collection::vec, option};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("12a")), 21);
+    assert_eq!(part1(sample_input("12a")), Ok(21));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("12")), 7032);
+    assert_eq!(part1(sample_input("12")), Ok(7032));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("12a")), 525152);
+    assert_eq!(part2(sample_input("12a")), Ok(525152));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("12")), 1493340882140);
+    assert_eq!(part2(sample_input("12")), Ok(1493340882140));
+  }
+
+  // Reference count: try every assignment of the unknown springs
+  fn brute_count(pat: &[Option<bool>], runs: &[usize]) -> usize {
+    let unknowns: Vec<_> = pat.iter().positions(Option::is_none).collect();
+    (0..1usize << unknowns.len())
+      .filter(|&bits| {
+        let mut filled: Vec<_> = pat.iter().map(|&sym| sym.unwrap_or(false)).collect();
+        for (i, &pos) in unknowns.iter().enumerate() {
+          filled[pos] = bits & (1 << i) != 0;
+        }
+        let filled_runs: Vec<_> = filled.into_iter()
+          .dedup_with_count()
+          .filter(|&(_, damaged)| damaged)
+          .map(|(len, _)| len)
+          .collect();
+        filled_runs == runs
+      })
+      .count()
+  }
+
+  proptest! {
+    #[test]
+    fn test_count_solutions_oracle(pat in vec(option::of(any::<bool>()), 1..14), runs in vec(1..5usize, 1..5)) {
+      prop_assert_eq!(sum_solutions(&[(pat.clone(), runs.clone())]), brute_count(&pat, &runs));
+    }
   }
 }
@@ -1,41 +1,57 @@
-use std::cmp::min;
-
-use itertools::{Itertools, iproduct};
-use nom::{IResult, character::complete::{one_of, line_ending}, multi::{many1, separated_list1}, sequence::terminated, combinator::{eof, map, verify}};
-
-pub fn part1(file: String) -> usize { process(file, 0) }
-pub fn part2(file: String) -> usize { process(file, 1) }
-
-fn process(file: String, target_mistakes: usize) -> usize {
-  parse(&file).unwrap().1.into_iter()
-    .map(|pat| {
-      (1..pat.len())
-        .find(|&y|
-          iproduct!(0..min(y, pat.len()-y), 0..pat[0].len())
-            .filter(|&(off, x)| pat[y+off][x] != pat[y-1-off][x])
-            .take(target_mistakes + 1)
-            .count() == target_mistakes
-        )
+use std::{cmp::min, io::BufRead};
+
+use itertools::iproduct;
+use nom::{IResult, character::complete::{one_of, line_ending}, multi::{many1, separated_list1}, sequence::terminated, combinator::{eof, map, map_opt}};
+
+use crate::{util::Grid, solution::{read_input, Solution, Answer}, error::{Error, Result, parse_all}};
+
+pub fn part1(input: impl BufRead) -> Result<usize> { process(&parse_all(Solver::DAY, 0, &read_input(input)?, parse)?, 0) }
+pub fn part2(input: impl BufRead) -> Result<usize> { process(&parse_all(Solver::DAY, 0, &read_input(input)?, parse)?, 1) }
+pub fn part1_file(file: String) -> Result<usize> { part1(file.as_bytes()) }
+pub fn part2_file(file: String) -> Result<usize> { part2(file.as_bytes()) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 13;
+  type Input = Vec<Pattern>;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> { parse_all(Solver::DAY, 0, input, parse) }
+  fn part1(pats: &Self::Input, _: &()) -> Result<Answer> { process(pats, 0).map(Answer::from) }
+  fn part2(pats: &Self::Input, _: &()) -> Result<Answer> { process(pats, 1).map(Answer::from) }
+}
+
+type Pattern = Grid<bool>;
+
+fn process(pats: &[Pattern], target_mistakes: usize) -> Result<usize> {
+  pats.iter()
+    .enumerate()
+    .map(|(i, pat)| {
+      reflection_row(pat, target_mistakes)
         .map(|y| y*100)
-        .or_else(||
-          (1..pat[0].len())
-            .find(|&x|
-              iproduct!(0..min(x, pat[0].len()-x), &pat)
-                .filter(|&(off, row)| row[x+off] != row[x-1-off])
-                .take(target_mistakes + 1)
-                .count() == target_mistakes
-            )
-        )
-        .unwrap()
+        .or_else(|| reflection_row(&pat.transpose(), target_mistakes))
+        .ok_or_else(|| Error::input(Solver::DAY, format!("pattern {} has no line of reflection with {} mismatched tiles", i + 1, target_mistakes)))
     })
     .sum()
 }
 
-fn parse(input: &str) -> IResult<&str, Vec<Vec<Vec<bool>>>> {
+// The number of rows above a horizontal line of reflection with exactly target_mistakes mismatched tiles
+fn reflection_row(pat: &Pattern, target_mistakes: usize) -> Option<usize> {
+  (1..pat.height())
+    .find(|&y|
+      iproduct!(0..min(y, pat.height()-y), 0..pat.width())
+        .filter(|&(off, x)| pat[(y+off, x)] != pat[(y-1-off, x)])
+        .take(target_mistakes + 1)
+        .count() == target_mistakes
+    )
+}
+
+fn parse(input: &str) -> IResult<&str, Vec<Pattern>> {
   terminated(
     separated_list1(
       line_ending,
-      verify(
+      map_opt(
         many1(terminated(
           many1(map(
             one_of("#."),
@@ -43,7 +59,7 @@ fn parse(input: &str) -> IResult<&str, Vec<Vec<Vec<bool>>>> {
             )),
           line_ending,
         )),
-        |pat: &Vec<Vec<bool>>| pat.iter().map(Vec::len).all_equal(),
+        Grid::from_rows,
       ),
     ),
     eof,
@@ -53,25 +69,33 @@ fn parse(input: &str) -> IResult<&str, Vec<Vec<Vec<bool>>>> {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_file;
+  use crate::test_util::sample_input;
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_file("13a")), 405);
+    assert_eq!(part1(sample_input("13a")), Ok(405));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_file("13")), 30802);
+    assert_eq!(part1(sample_input("13")), Ok(30802));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_file("13a")), 400);
+    assert_eq!(part2(sample_input("13a")), Ok(400));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_file("13")), 37876);
+    assert_eq!(part2(sample_input("13")), Ok(37876));
+  }
+
+  #[test]
+  fn test_no_reflection() {
+    let err = part1("##\n..\n\n#\n".as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 13: pattern 2 has no line of reflection with 0 mismatched tiles");
+    let err = part2("##\n##\n".as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 13: pattern 1 has no line of reflection with 1 mismatched tiles");
   }
 }
@@ -1,15 +1,35 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, io::BufRead};
 
-pub fn part1(lines: impl Iterator<Item=String>) -> usize {
-  let plat = parse(lines);
-  (0..plat[0].len())
+use crate::{util::{Grid, TryInsert}, solution::{read_input, joined_lines, Solution, Answer}, error::Result};
+
+pub fn part1(input: impl BufRead) -> Result<usize> { Ok(north_load(&parse(read_input(input)?.lines())?)) }
+pub fn part2(input: impl BufRead) -> Result<usize> { Ok(spun_north_load(parse(read_input(input)?.lines())?)) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part2(joined_lines(lines)) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 14;
+  type Input = Platform;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> { parse(input.lines()) }
+  fn part1(plat: &Self::Input, _: &()) -> Result<Answer> { Ok(north_load(plat).into()) }
+  fn part2(plat: &Self::Input, _: &()) -> Result<Answer> { Ok(spun_north_load(plat.clone()).into()) }
+}
+
+type Platform = Grid<Option<bool>>;
+
+fn north_load(plat: &Platform) -> usize {
+  (0..plat.width())
     .map(|x| {
       let mut load = 0;
       let mut next_slot = 0;
-      for (y, row) in plat.iter().enumerate() {
-        match row[x] {
+      for (y, &tile) in plat.col(x).enumerate() {
+        match tile {
           Some(true) => {
-            load += plat.len() - next_slot;
+            load += plat.height() - next_slot;
             next_slot += 1;
           }
           None => (),
@@ -21,127 +41,84 @@ pub fn part1(lines: impl Iterator<Item=String>) -> usize {
     .sum()
 }
 
-pub fn part2(lines: impl Iterator<Item=String>) -> usize {
+fn tilt_north(plat: &mut Platform) {
+  for x in 0..plat.width() {
+    let mut next_slot = 0;
+    for y in 0..plat.height() {
+      match plat[(y, x)] {
+        Some(true) => {
+          plat[(y, x)] = None;
+          plat[(next_slot, x)] = Some(true); // Do second in case stone isn't moving
+          next_slot += 1;
+        },
+        None => (),
+        Some(false) => next_slot = y+1,
+      }
+    }
+  }
+}
+
+fn spun_north_load(plat: Platform) -> usize {
   let target = 1_000_000_000;
-  let plat = parse(lines);
   let mut log = vec![plat.clone()];
   let mut uniques = HashMap::from([(plat, 0)]);
 
   let prefix_len = loop {
     let mut plat = log.last().unwrap().clone();
 
-    //N
-    for x in 0..plat[0].len() {
-      let mut next_slot = 0;
-      for y in 0..plat.len() {
-        match plat[y][x] {
-          Some(true) => {
-            plat[y][x] = None;
-            plat[next_slot][x] = Some(true); // Do second in case stone isn't moving
-            next_slot += 1;
-          },
-          None => (),
-          Some(false) => next_slot = y+1,
-        }
-      }
-    }
-    //W
-    for row in plat.iter_mut() {
-      let mut next_slot = 0;
-      for x in 0..row.len() {
-        match row[x] {
-          Some(true) => {
-            row[x] = None;
-            row[next_slot] = Some(true); // Do second in case stone isn't moving
-            next_slot += 1;
-          },
-          None => (),
-          Some(false) => next_slot = x+1,
-        }
-      }
-    }
-    //S
-    for x in 0..plat[0].len() {
-      let mut next_slot = plat.len()-1;
-      for y in (0..plat.len()).rev() {
-        match plat[y][x] {
-          Some(true) => {
-            plat[y][x] = None;
-            plat[next_slot][x] = Some(true); // Do second in case stone isn't moving
-            next_slot = next_slot.saturating_sub(1);
-          },
-          None => (),
-          Some(false) => next_slot = y.saturating_sub(1),
-        }
-      }
-    }
-    //E
-    for row in plat.iter_mut() {
-      let mut next_slot = row.len()-1;
-      for x in (0..row.len()).rev() {
-        match row[x] {
-          Some(true) => {
-            row[x] = None;
-            row[next_slot] = Some(true); // Do second in case stone isn't moving
-            next_slot = next_slot.saturating_sub(1);
-          },
-          None => (),
-          Some(false) => next_slot = x.saturating_sub(1),
-        }
-      }
+    // Rotating clockwise brings W, then S, then E to the top, and the fourth turn restores the original orientation
+    for _ in 0..4 {
+      tilt_north(&mut plat);
+      plat = plat.rotate_cw();
     }
 
-    if let Err(e) = uniques.try_insert(plat.clone(), uniques.len()) {
-      break *e.entry.get();
+    if let Err((&mut prefix_len, _)) = uniques.insert_vacant(plat.clone(), uniques.len()) {
+      break prefix_len;
     }
     log.push(plat);
   };
   let cycle_len = uniques.len() - prefix_len;
   let target_i = if target < prefix_len { target } else { (target-prefix_len) % cycle_len + prefix_len };
 
-  log[target_i].iter()
+  log[target_i].rows()
     .enumerate()
     .map(|(y, row)|
       row.iter().filter(|&&tile| tile == Some(true)).count()
-      * (log[0].len() - y))
+      * (log[0].height() - y))
     .sum()
 }
 
-fn parse(lines: impl Iterator<Item=String>) -> Vec<Vec<Option<bool>>> {
-  lines
-    .map(|line| line.chars()
-      .map(|c| match c {
-        'O' => Some(true),
-        '#' => Some(false),
-        '.' => None,
-        _ => panic!(),
-      })
-      .collect())
-    .collect()
+fn parse(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Platform> {
+  Grid::parse(Solver::DAY, lines, |c| match c {
+    'O' => Some(Some(true)),
+    '#' => Some(Some(false)),
+    '.' => Some(None),
+    _ => None,
+  })
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::test_util::sample_input;
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("14a")), 136);
+    assert_eq!(part1(sample_input("14a")), Ok(136));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("14")), 105623);
+    assert_eq!(part1(sample_input("14")), Ok(105623));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("14a")), 64);
+    assert_eq!(part2(sample_input("14a")), Ok(64));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("14")), 98029);
+    assert_eq!(part2(sample_input("14")), Ok(98029));
   }
 }
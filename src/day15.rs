@@ -1,17 +1,37 @@
-use crate::util::usize;
+use std::io::BufRead;
+
+use crate::{util::usize, solution::{read_input, Solution, Answer}, error::{Result, parse_all}};
 
 use nom::{IResult, character::complete::{char, line_ending, alpha1}, multi::separated_list0, sequence::{terminated, pair, preceded}, combinator::{eof, map}, bytes::complete::take_while, branch::alt};
 
-pub fn part1(file: String) -> usize {
-  parse1(&file).unwrap().1.into_iter()
+pub fn part1(input: impl BufRead) -> Result<usize> { sum_hashes(&read_input(input)?) }
+pub fn part2(input: impl BufRead) -> Result<usize> { focusing_power(&read_input(input)?) }
+pub fn part1_file(file: String) -> Result<usize> { part1(file.as_bytes()) }
+pub fn part2_file(file: String) -> Result<usize> { part2(file.as_bytes()) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 15;
+  // The two parts split steps differently, so parsing waits for the part
+  type Input = String;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> { Ok(input.to_owned()) }
+  fn part1(file: &Self::Input, _: &()) -> Result<Answer> { sum_hashes(file).map(Answer::from) }
+  fn part2(file: &Self::Input, _: &()) -> Result<Answer> { focusing_power(file).map(Answer::from) }
+}
+
+fn sum_hashes(file: &str) -> Result<usize> {
+  Ok(parse_all(Solver::DAY, 0, file, parse1)?.into_iter()
     .map(hash)
     .map(Into::<usize>::into)
-    .sum()
+    .sum())
 }
 
-pub fn part2(file: String) -> usize {
+fn focusing_power(file: &str) -> Result<usize> {
   let mut boxes: Vec<Vec<(&str, usize)>> = vec![vec![]; 256];
-  for (label, op) in parse2(&file).unwrap().1 {
+  for (label, op) in parse_all(Solver::DAY, 0, file, parse2)? {
     let box_num: usize = hash(label).into();
     let b = &mut boxes[box_num];
     match (op, b.iter_mut().enumerate().find(|(_, slot)| slot.0 == label)) {
@@ -21,12 +41,12 @@ pub fn part2(file: String) -> usize {
       (Some(focal_length), Some((_, slot))) => slot.1 = focal_length,
     }
   }
-  boxes.into_iter()
+  Ok(boxes.into_iter()
     .enumerate()
     .flat_map(|(box_num, b)| b.into_iter()
       .enumerate()
       .map(move |(lens_num, (_, focal_length))| (box_num + 1)*(lens_num + 1)*focal_length))
-    .sum()
+    .sum())
 }
 
 fn hash(s: &str) -> u8 {
@@ -42,7 +62,7 @@ fn parse1(input: &str) -> IResult<&str, Vec<&str>> {
   terminated(
     separated_list0(
       char(','),
-      take_while(|c: char| c.is_alphabetic() || c.is_digit(10) || c == '-' || c == '='),
+      take_while(|c: char| c.is_ascii_alphanumeric() || c == '-' || c == '='),
     ),
     pair(line_ending, eof),
   )(input)
@@ -56,7 +76,7 @@ fn parse2(input: &str) -> IResult<&str, Vec<(&str, Option<usize>)>> {
         alpha1,
         alt((
           map(char('-'), |_| None),
-          map(preceded(char('='), usize), |n| Some(n)),
+          map(preceded(char('='), usize), Some),
         )),
       )
     ),
@@ -67,25 +87,25 @@ fn parse2(input: &str) -> IResult<&str, Vec<(&str, Option<usize>)>> {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_file;
+  use crate::test_util::sample_input;
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_file("15a")), 1320);
+    assert_eq!(part1(sample_input("15a")), Ok(1320));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_file("15")), 502139);
+    assert_eq!(part1(sample_input("15")), Ok(502139));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_file("15a")), 145);
+    assert_eq!(part2(sample_input("15a")), Ok(145));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_file("15")), 284132);
+    assert_eq!(part2(sample_input("15")), Ok(284132));
   }
 }
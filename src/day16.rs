@@ -1,31 +1,48 @@
-use std::{collections::HashMap, iter::once};
+use std::{collections::HashMap, iter::once, io::BufRead};
 
-use enum_map::{EnumMap, Enum};
+use enum_map::EnumMap;
 
-use crate::util::Coord;
+use crate::{util::{Coord, Dir, Grid, TryInsert}, solution::{read_input, joined_lines, Solution, Answer}, error::Result};
 
-pub fn part1(lines: impl Iterator<Item=String>) -> usize {
-  process(&parse(lines), (0, 0), Dir::E)
+pub fn part1(input: impl BufRead) -> Result<usize> { Ok(energized_from_corner(&parse(read_input(input)?.lines())?)) }
+pub fn part2(input: impl BufRead) -> Result<usize> { Ok(max_energized(&parse(read_input(input)?.lines())?)) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part2(joined_lines(lines)) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 16;
+  type Input = Contraption;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> { parse(input.lines()) }
+  fn part1(map: &Self::Input, _: &()) -> Result<Answer> { Ok(energized_from_corner(map).into()) }
+  fn part2(map: &Self::Input, _: &()) -> Result<Answer> { Ok(max_energized(map).into()) }
 }
-pub fn part2(lines: impl Iterator<Item=String>) -> usize {
-  let map = parse(lines);
-  (0..map.len())
-    .flat_map(|y| once(((y, 0), Dir::E)).chain(once(((y, map[0].len()-1), Dir::W))))
+
+pub struct Contraption(Grid<Tile>);
+
+fn energized_from_corner(Contraption(map): &Contraption) -> usize {
+  process(map, (0, 0), Dir::E)
+}
+
+fn max_energized(Contraption(map): &Contraption) -> usize {
+  (0..map.height())
+    .flat_map(|y| once(((y, 0), Dir::E)).chain(once(((y, map.width()-1), Dir::W))))
     .chain(
-      (0..map[0].len())
-        .flat_map(|x| once(((0, x), Dir::S)).chain(once(((map.len()-1, x), Dir::N))))
+      (0..map.width())
+        .flat_map(|x| once(((0, x), Dir::S)).chain(once(((map.height()-1, x), Dir::N))))
     )
-    .map(|(pos, dir)| process(&map, pos, dir))
+    .map(|(pos, dir)| process(map, pos, dir))
     .max().unwrap()
 }
 
-fn parse(lines: impl Iterator<Item=String>) -> Vec<Vec<Tile>> {
-  lines
-    .map(|line| line.chars().map(Tile::try_parse).map(Option::unwrap).collect())
-    .collect()
+fn parse(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Contraption> {
+  Ok(Contraption(Grid::parse(Solver::DAY, lines, Tile::try_parse)?))
 }
 
-fn process(map: &Vec<Vec<Tile>>, init_pos: Coord, init_dir: Dir) -> usize {
+fn process(map: &Grid<Tile>, init_pos: Coord, init_dir: Dir) -> usize {
   let mut energized = HashMap::new();
   energized.insert(init_pos, {
     let mut init_dirs = EnumMap::default();
@@ -34,7 +51,7 @@ fn process(map: &Vec<Vec<Tile>>, init_pos: Coord, init_dir: Dir) -> usize {
   });
   let mut to_advance = vec![(init_pos, init_dir)];
   while let Some((pos, dir)) = to_advance.pop() {
-    let leave_dirs: &[Dir] = match (dir, map[pos.0][pos.1]) {
+    let leave_dirs: &[Dir] = match (dir, map[pos]) {
       (Dir::E | Dir::W, Tile::SplitNS) => &[Dir::N, Dir::S],
       (Dir::N | Dir::S, Tile::SplitEW) => &[Dir::E, Dir::W],
       (Dir::E, Tile::Empty | Tile::SplitEW) => &[Dir::E],
@@ -51,14 +68,13 @@ fn process(map: &Vec<Vec<Tile>>, init_pos: Coord, init_dir: Dir) -> usize {
       (Dir::N, Tile::MirrorES) => &[Dir::W],
     };
     for &leave_dir in leave_dirs {
-      if let Some(new_pos) = try_step(pos, leave_dir, (map.len(), map[0].len())) {
-        match energized.try_insert(new_pos, EnumMap::default()) {
+      if let Some(new_pos) = map.step(pos, leave_dir) {
+        match energized.insert_vacant(new_pos, EnumMap::default()) {
           Ok(dirs) => {
             dirs[leave_dir] = true;
             to_advance.push((new_pos, leave_dir));
           },
-          Err(e) => {
-            let dirs = e.entry.into_mut();
+          Err((dirs, _)) => {
             if !dirs[leave_dir] {
               dirs[leave_dir] = true;
               to_advance.push((new_pos, leave_dir));
@@ -68,8 +84,8 @@ fn process(map: &Vec<Vec<Tile>>, init_pos: Coord, init_dir: Dir) -> usize {
       }
     }
   }
-  /*for y in 0..map.len() {
-    for x in 0..map[0].len() {
+  /*for y in 0..map.height() {
+    for x in 0..map.width() {
       print!("{}", match energized.get(&(y, x)) {
         None => '.',
         Some(dirs) => match dirs.iter().filter(|&(_, &v)| v).map(|(k, _)| k).exactly_one() {
@@ -86,19 +102,6 @@ fn process(map: &Vec<Vec<Tile>>, init_pos: Coord, init_dir: Dir) -> usize {
   energized.len()
 }
 
-// TODO Refactor to share with day 08
-fn try_step((y, x): Coord, dir: Dir, (max_y, max_x): Coord) -> Option<Coord> {
-  match dir {
-    Dir::E => x.checked_add(1).map(|x| (y, x)).filter(|&(_, x)| x < max_x),
-    Dir::N => y.checked_sub(1).map(|y| (y, x)),
-    Dir::W => x.checked_sub(1).map(|x| (y, x)),
-    Dir::S => y.checked_add(1).map(|y| (y, x)).filter(|&(y, _)| y < max_y),
-  }
-}
-
-#[derive(Enum, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
-enum Dir { E, N, W, S }
-
 #[derive(Clone, Copy)]
 enum Tile { Empty, MirrorEN, MirrorES, SplitEW, SplitNS }
 
@@ -118,25 +121,34 @@ impl Tile {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::test_util::{sample_file, sample_input};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("16a")), 46);
+    assert_eq!(part1(sample_input("16a")), Ok(46));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("16")), 7185);
+    assert_eq!(part1(sample_input("16")), Ok(7185));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("16a")), 51);
+    assert_eq!(part2(sample_input("16a")), Ok(51));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("16")), 7616);
+    assert_eq!(part2(sample_input("16")), Ok(7616));
+  }
+
+  #[test]
+  fn test_parse_error() {
+    let err = part1(sample_file("16a").replace("|-", "|+").as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 16, line 3, column 7: unexpected tile at \"+...\"");
+    let file = sample_file("16a").lines().take(3).chain([".."]).collect::<Vec<_>>().join("\n");
+    let err = part1(file.as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 16, line 4, column 1: row length differs from the first row at \"..\"");
   }
 }
@@ -1,47 +1,57 @@
-use std::{collections::{BinaryHeap, HashMap}, cmp::Ordering};
+use std::{collections::{BinaryHeap, HashMap}, cmp::Ordering, io::BufRead};
 
-use crate::util::Coord;
+use crate::{util::{Coord, Dir, Grid, TryInsert}, solution::{read_input, joined_lines, Solution, Answer}, error::Result};
 
-pub fn part1(lines: impl Iterator<Item=String>) -> usize { process(lines, 1, 3) }
-pub fn part2(lines: impl Iterator<Item=String>) -> usize { process(lines, 4, 10) }
+pub fn part1(input: impl BufRead) -> Result<usize> { Ok(process(&parse(read_input(input)?.lines())?, 1, 3)) }
+pub fn part2(input: impl BufRead) -> Result<usize> { Ok(process(&parse(read_input(input)?.lines())?, 4, 10)) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part2(joined_lines(lines)) }
 
-fn process(lines: impl Iterator<Item=String>, min_run: u8, max_run: u8) -> usize {
-  let map: Vec<Vec<u8>> = lines
-    .map(|line| line.chars()
-      .map(|c| c.to_digit(10).unwrap().try_into().unwrap())
-      .collect())
-    .collect();
+pub struct Solver;
 
-  let bounds = (map.len(), map[0].len());
-  let dest = (bounds.0-1, bounds.1-1);
+impl Solution for Solver {
+  const DAY: u8 = 17;
+  type Input = Grid<u8>;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> { parse(input.lines()) }
+  fn part1(map: &Self::Input, _: &()) -> Result<Answer> { Ok(process(map, 1, 3).into()) }
+  fn part2(map: &Self::Input, _: &()) -> Result<Answer> { Ok(process(map, 4, 10).into()) }
+}
+
+fn parse(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Grid<u8>> {
+  Grid::parse(Solver::DAY, lines, |c| c.to_digit(10).map(|d| d.try_into().unwrap()))
+}
+
+fn process(map: &Grid<u8>, min_run: u8, max_run: u8) -> usize {
+  let dest = (map.height()-1, map.width()-1);
   let mut seen = HashMap::new();
   let mut to_explore = BinaryHeap::from([State::default()]);
   loop {
     let state = to_explore.pop().unwrap();
     if state.pos == dest { break state.heat_loss; }
 
-    if let Err(mut e) = seen.try_insert((state.pos, state.face), state.run) {
-      let old_state = e.entry.get_mut();
-      if *old_state <= state.run {
+    if let Err((old_run, run)) = seen.insert_vacant((state.pos, state.face), state.run) {
+      if *old_run <= run {
         continue;
       }
-      *old_state = e.value;
+      *old_run = run;
     }
 
     'try_turn: for face in [state.face.turn_ccw(), state.face.turn_cw()] {
       let mut heat_loss = state.heat_loss;
       let mut pos = state.pos;
       for _ in 0..min_run {
-        pos = if let Some(pos) = try_step(pos, face, bounds) { pos } else { continue 'try_turn };
-        heat_loss += usize::from(map[pos.0][pos.1]);
+        pos = if let Some(pos) = map.step(pos, face) { pos } else { continue 'try_turn };
+        heat_loss += usize::from(map[pos]);
       }
       to_explore.push(State { pos, face, heat_loss, run: min_run });
     }
-    if state.run < max_run && let Some(pos) = try_step(state.pos, state.face, bounds) {
+    if let Some(pos) = map.step(state.pos, state.face).filter(|_| state.run < max_run) {
       to_explore.push(State { pos,
         face: state.face,
         run: state.run + 1, 
-        heat_loss: state.heat_loss + usize::from(map[pos.0][pos.1]),
+        heat_loss: state.heat_loss + usize::from(map[pos]),
       });
     }
   }
@@ -75,62 +85,29 @@ impl Ord for State {
   }
 }
 
-// TODO Refactor to share with day 08
-fn try_step((y, x): Coord, dir: Dir, (max_y, max_x): Coord) -> Option<Coord> {
-  match dir {
-    Dir::E => x.checked_add(1).map(|x| (y, x)).filter(|&(_, x)| x < max_x),
-    Dir::N => y.checked_sub(1).map(|y| (y, x)),
-    Dir::W => x.checked_sub(1).map(|x| (y, x)),
-    Dir::S => y.checked_add(1).map(|y| (y, x)).filter(|&(y, _)| y < max_y),
-  }
-}
-
-#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash, Default)]
-enum Dir { #[default] E, N, W, S }
-
-impl Dir {
-  fn turn_ccw(&self) -> Self {
-    match self {
-      Self::E => Self::N,
-      Self::N => Self::W,
-      Self::W => Self::S,
-      Self::S => Self::E,
-    }
-  }
-
-  fn turn_cw(&self) -> Self {
-    match self {
-      Self::E => Self::S,
-      Self::N => Self::E,
-      Self::W => Self::N,
-      Self::S => Self::W,
-    }
-  }
-}
-
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::test_util::sample_input;
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("17a")), 102);
+    assert_eq!(part1(sample_input("17a")), Ok(102));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("17")), 1023);
+    assert_eq!(part1(sample_input("17")), Ok(1023));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("17a")), 94);
-    assert_eq!(part2(sample_lines("17b")), 71);
+    assert_eq!(part2(sample_input("17a")), Ok(94));
+    assert_eq!(part2(sample_input("17b")), Ok(71));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("17")), 1165);
+    assert_eq!(part2(sample_input("17")), Ok(1165));
   }
 }
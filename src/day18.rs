@@ -1,32 +1,55 @@
+use std::io::BufRead;
+
 use bitvec::{prelude::*, vec::BitVec};
 use itertools::Itertools;
 use nom::{IResult, sequence::{terminated, pair}, character::complete::one_of, combinator::{eof, map_res, map}, bytes::complete::{tag, take}, character::complete::char};
 
-use crate::util::usize;
+use crate::{util::{usize, Dir}, solution::{read_input, joined_lines, Solution, Answer}, error::{self, Result}};
+
+pub fn part1(input: impl BufRead) -> Result<usize> { Ok(process(&parse_lines(read_input(input)?.lines())?, 0)) }
+pub fn part2(input: impl BufRead) -> Result<usize> { Ok(process(&parse_lines(read_input(input)?.lines())?, 1)) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part2(joined_lines(lines)) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 18;
+  type Input = Vec<Instr>;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> { parse_lines(input.lines()) }
+  fn part1(instrs: &Self::Input, _: &()) -> Result<Answer> { Ok(process(instrs, 0).into()) }
+  fn part2(instrs: &Self::Input, _: &()) -> Result<Answer> { Ok(process(instrs, 1).into()) }
+}
+
+// The plan as written, and as decoded from the colour
+pub struct Instr([(Dir, usize); 2]);
 
-pub fn part1(lines: impl Iterator<Item=String>) -> usize { process(lines, 0) }
-pub fn part2(lines: impl Iterator<Item=String>) -> usize { process(lines, 1) }
+fn parse_lines(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Vec<Instr>> {
+  Ok(error::parse_lines(Solver::DAY, lines, parse)?.into_iter().map(Instr).collect())
+}
 
-fn process(lines: impl Iterator<Item=String>, instr_idx: usize) -> usize {
+fn process(instrs: &[Instr], instr_idx: usize) -> usize {
   let mut curs: (isize, isize) = (0, 0);
   let mut trench = Trench::default();
   trench.dig_horiz(curs, 1);
-  for line in lines {
-    let (dir, run) = parse(&line).unwrap().1[instr_idx];
+  for Instr(instr) in instrs {
+    let (dir, run) = instr[instr_idx];
     match dir {
-      Dir::R => {
+      Dir::E => {
         trench.dig_horiz((curs.0, curs.1 + 1), run);
         curs.1 += isize::try_from(run).unwrap();
       },
-      Dir::L => {
+      Dir::W => {
         curs.1 -= isize::try_from(run).unwrap();
         trench.dig_horiz(curs, run);
       },
-      Dir::D => {
+      Dir::S => {
         trench.dig_vert((curs.0 + 1, curs.1), run);
         curs.0 += isize::try_from(run).unwrap();
       },
-      Dir::U => {
+      Dir::N => {
         curs.0 -= isize::try_from(run).unwrap();
         trench.dig_vert(curs, run);
       },
@@ -119,7 +142,7 @@ impl Trench {
     }
   }
   fn materialize_row_at(&mut self, y: isize, py: usize) {
-    if let Some(&(y0, _)) = self.rows.get(py) && y == y0 { return; }
+    if self.rows.get(py).is_some_and(|&(y0, _)| y == y0) { return; }
     self.materialize_row_at_impl(y, py);
   }
   fn materialize_row_at_impl(&mut self, y: isize, py: usize) {
@@ -133,7 +156,7 @@ impl Trench {
     }
   }
   fn materialize_col_at(&mut self, x: isize, px: usize) {
-    if let Some(&x0) = self.col_idxs.get(px) && x == x0 { return; }
+    if self.col_idxs.get(px).is_some_and(|&x0| x == x0) { return; }
     self.materialize_col_at_impl(x, px);
   }
   fn materialize_col_at_impl(&mut self, x: isize, px: usize) {
@@ -150,7 +173,7 @@ fn parse(input: &str) -> IResult<&str, [(Dir, usize); 2]> {
   let (input, dir0) = terminated(
     map(
       one_of("UDLR"),
-      |c| match c { 'U' => Dir::U, 'D' => Dir::D, 'L' => Dir::L, 'R' => Dir::R, _ => unreachable!() },
+      |c| match c { 'U' => Dir::N, 'D' => Dir::S, 'L' => Dir::W, 'R' => Dir::E, _ => unreachable!() },
     ),
     char(' '),
   )(input)?;
@@ -159,7 +182,7 @@ fn parse(input: &str) -> IResult<&str, [(Dir, usize); 2]> {
   let (input, dir1) = terminated(
     map(
       one_of("0123"),
-      |c| match c { '0' => Dir::R, '1' => Dir::D, '2' => Dir::L, '3' => Dir::U, _ => unreachable!() },
+      |c| match c { '0' => Dir::E, '1' => Dir::S, '2' => Dir::W, '3' => Dir::N, _ => unreachable!() },
     ),
     pair(
       char(')'),
@@ -169,31 +192,70 @@ fn parse(input: &str) -> IResult<&str, [(Dir, usize); 2]> {
   Ok((input, [(dir0, run0), (dir1, run1)]))
 }
 
-#[derive(Clone, Copy)]
-enum Dir { R, U, L, D }
-
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::test_util::sample_input;
+  use std::collections::HashSet;
+  use proptest::{prelude::*, collection::vec, sample::select};
+  use strum::IntoEnumIterator;
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("18a")), 62);
+    assert_eq!(part1(sample_input("18a")), Ok(62));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("18")), 58550);
+    assert_eq!(part1(sample_input("18")), Ok(58550));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("18a")), 952408144115);
+    assert_eq!(part2(sample_input("18a")), Ok(952408144115));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("18")), 47452118468566);
+    assert_eq!(part2(sample_input("18")), Ok(47452118468566));
+  }
+
+  // Reference area: dig every cube of the trench, flood the exterior from just outside its bounding box, and count
+  // everything else
+  fn brute_area(plan: &[(Dir, usize)]) -> usize {
+    let mut dug = HashSet::from([(0isize, 0isize)]);
+    let mut curs = (0, 0);
+    for &(dir, run) in plan {
+      for _ in 0..run {
+        match dir {
+          Dir::E => curs.1 += 1,
+          Dir::N => curs.0 -= 1,
+          Dir::W => curs.1 -= 1,
+          Dir::S => curs.0 += 1,
+        }
+        dug.insert(curs);
+      }
+    }
+    let (min_y, max_y) = dug.iter().map(|&(y, _)| y).minmax().into_option().unwrap();
+    let (min_x, max_x) = dug.iter().map(|&(_, x)| x).minmax().into_option().unwrap();
+    let in_box = |(y, x): Coord| (min_y - 1..=max_y + 1).contains(&y) && (min_x - 1..=max_x + 1).contains(&x);
+
+    let mut exterior = HashSet::new();
+    let mut to_check = vec![(min_y - 1, min_x - 1)];
+    while let Some((y, x)) = to_check.pop() {
+      if in_box((y, x)) && !dug.contains(&(y, x)) && exterior.insert((y, x)) {
+        to_check.extend([(y - 1, x), (y + 1, x), (y, x - 1), (y, x + 1)]);
+      }
+    }
+    let box_area = (max_y - min_y + 3) * (max_x - min_x + 3);
+    usize::try_from(box_area).unwrap() - exterior.len()
+  }
+
+  proptest! {
+    #[test]
+    fn test_lagoon_area_oracle(plan in vec((select(Dir::iter().collect::<Vec<_>>()), 1..6usize), 1..12)) {
+      let instrs: Vec<_> = plan.iter().map(|&step| Instr([step; 2])).collect();
+      prop_assert_eq!(process(&instrs, 0), brute_area(&plan));
+    }
   }
 }
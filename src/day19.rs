@@ -1,14 +1,32 @@
-use std::{collections::HashMap, ops::RangeInclusive, cmp::{max, min}};
+use std::{collections::HashMap, ops::RangeInclusive, cmp::{max, min}, io::BufRead};
 
-use crate::util::usize;
+use crate::{util::usize, solution::{read_input, Solution, Answer}, error::{Error, Result, parse_all}};
 
 use enum_map::{EnumMap, Enum, enum_map};
-use nom::{IResult, character::complete::{char, line_ending, one_of}, multi::{many0, many1, separated_list1}, sequence::{terminated, pair, separated_pair, delimited}, combinator::{eof, map, value}, bytes::complete::take_while, branch::alt};
+use nom::{IResult, Offset, character::complete::{char, line_ending, one_of}, multi::{many0, many1, separated_list1}, sequence::{terminated, pair, separated_pair, delimited}, combinator::{eof, map, value}, bytes::complete::take_while, branch::alt};
 
-pub fn part1(file: String) -> usize {
-  let (workflows, parts) = parse(&file).unwrap().1;
+pub fn part1(input: impl BufRead) -> Result<usize> { sum_accepted(&read_input(input)?) }
+pub fn part2(input: impl BufRead) -> Result<usize> { count_acceptable(&read_input(input)?) }
+pub fn part1_file(file: String) -> Result<usize> { part1(file.as_bytes()) }
+pub fn part2_file(file: String) -> Result<usize> { part2(file.as_bytes()) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 19;
+  // Workflows borrow their names from the text, so parsing waits for the part
+  type Input = String;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> { parse_system(input).map(|_| input.to_owned()) }
+  fn part1(file: &Self::Input, _: &()) -> Result<Answer> { sum_accepted(file).map(Answer::from) }
+  fn part2(file: &Self::Input, _: &()) -> Result<Answer> { count_acceptable(file).map(Answer::from) }
+}
+
+fn sum_accepted(file: &str) -> Result<usize> {
+  let (workflows, parts) = parse_system(file)?;
   let in_workflow = &workflows["in"];
-  parts.into_iter()
+  Ok(parts.into_iter()
     .filter(|part| {
       let mut workflow = in_workflow;
       loop {
@@ -20,15 +38,35 @@ pub fn part1(file: String) -> usize {
       }
     })
     .flat_map(|part| part.into_values())
-    .sum()
+    .sum())
 }
 
-pub fn part2(file: String) -> usize {
-  let (workflows, _) = parse(&file).unwrap().1;
-  workflows["in"].count_acceptances(
+fn count_acceptable(file: &str) -> Result<usize> {
+  let (workflows, _) = parse_system(file)?;
+  Ok(workflows["in"].count_acceptances(
     enum_map! { _ => 1..=4000 },
     |tag| &workflows[tag],
-  )
+  ))
+}
+
+fn parse_system(file: &str) -> Result<(HashMap<&str, Workflow<'_>>, Vec<Part>)> {
+  let (workflows, parts) = parse_all(Solver::DAY, 0, file, parse)?;
+  if !workflows.contains_key("in") {
+    return Err(Error::input(Solver::DAY, "no workflow named \"in\""));
+  }
+  for workflow in workflows.values() {
+    let unknown = workflow.steps.iter()
+      .map(|(_, dest)| dest)
+      .chain([&workflow.fallback])
+      .find_map(|dest| match dest {
+        Dest::Continue(next) if !workflows.contains_key(next) => Some(next),
+        _ => None,
+      });
+    if let Some(next) = unknown {
+      return Err(Error::parse(Solver::DAY, 0, file, file.offset(next), format!("unknown workflow {:?}", next)));
+    }
+  }
+  Ok((workflows, parts))
 }
 
 #[derive(Clone, Copy)]
@@ -43,7 +81,7 @@ impl<'a> Dest<'a> {
     alt((
       value(Self::Accept, char('A')),
       value(Self::Reject, char('R')),
-      map(parse_name, |tag| Self::Continue(tag)),
+      map(parse_name, Self::Continue),
     ))(input)
   }
 
@@ -168,7 +206,7 @@ fn parse_part(input: &str) -> IResult<&str, Part> {
   Ok((input, part))
 }
 
-fn parse(input: &str) -> IResult<&str, (HashMap<&str, Workflow>, Vec<Part>)> {
+fn parse(input: &str) -> IResult<&str, (HashMap<&str, Workflow<'_>>, Vec<Part>)> {
   pair(
     terminated(
       map(
@@ -202,25 +240,33 @@ fn parse_name(input: &str) -> IResult<&str, &str> {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_file;
+  use crate::test_util::{sample_file, sample_input};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_file("19a")), 19114);
+    assert_eq!(part1(sample_input("19a")), Ok(19114));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_file("19")), 368964);
+    assert_eq!(part1(sample_input("19")), Ok(368964));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_file("19a")), 167409079868000);
+    assert_eq!(part2(sample_input("19a")), Ok(167409079868000));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_file("19")), 127675188176682);
+    assert_eq!(part2(sample_input("19")), Ok(127675188176682));
+  }
+
+  #[test]
+  fn test_parse_error() {
+    let err = part1(sample_file("19a").replace("{x=787,", "{x=787;").as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 19, line 13, column 7: unexpected input (Char) at \";m=2655,a=1222,s=287\"");
+    let err = part1(sample_file("19a").replace("a<2006:qkq", "a<2006:qqq").as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 19, line 1, column 11: unknown workflow \"qqq\" at \"qqq,m>2090:A,rfg}\"");
   }
 }
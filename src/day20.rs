@@ -1,151 +1,107 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::{collections::{HashMap, HashSet, VecDeque}, io::BufRead};
 
 use nom::{IResult, sequence::{terminated, preceded}, combinator::map, bytes::complete::{tag, take_while}, character::complete::char, branch::alt, multi::separated_list1};
-use num::integer::lcm;
+use num::{BigUint, Integer, One};
 
-pub fn part1(lines: impl Iterator<Item=String>) -> usize {
-  let lines: Vec<_> = lines.collect();
-  let (mut mods, backrefs) = parse(lines.iter());
+use crate::{solution::{read_input, joined_lines, Solution, Answer}, error::{Error, Result, parse_all}};
 
-  let mut pulses = VecDeque::new();
+pub fn part1(input: impl BufRead) -> Result<usize> { count_pulses(&read_input(input)?) }
+pub fn part2(input: impl BufRead) -> Result<BigUint> { presses_to_rx(&read_input(input)?) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<BigUint> { part2(joined_lines(lines)) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 20;
+  // Modules borrow their names from the text, so parsing waits for the part
+  type Input = String;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> {
+    parse(input.lines())?;
+    Ok(input.to_owned())
+  }
+  fn part1(text: &Self::Input, _: &()) -> Result<Answer> { count_pulses(text).map(Answer::from) }
+  fn part2(text: &Self::Input, _: &()) -> Result<Answer> { presses_to_rx(text)?.try_into() }
+}
+
+fn count_pulses(text: &str) -> Result<usize> {
+  let (mut mods, backrefs) = parse(text.lines())?;
   let mut lows = 0;
   let mut highs = 0;
   for _ in 0..1000 {
-    pulses.push_back((None, "broadcaster", false));
-    while let Some((from, to, hi)) = pulses.pop_front() {
-      *(if hi { &mut highs } else { &mut lows }) += 1;
-      if let Some(module) = mods.get_mut(to) {
-        let sig = match &mut module.logic {
-          ModuleLogic::FlipFlop(mem) => if hi { None } else { *mem = !*mem; Some(*mem) },
-          ModuleLogic::Conjunction(mem) => {
-            if hi { mem.insert(from.unwrap()); } else { mem.remove(from.unwrap()); }
-            Some(if mem.len() == backrefs[to].len() { false } else { true })
-          },
-          ModuleLogic::Broadcast => Some(hi),
-        };
-        if let Some(sig) = sig {
-          pulses.extend(module.dests.iter().map(|&dest| (Some(to), dest, sig)));
-        }
+    press(&mut mods, &backrefs, |_, _, hi| *(if hi { &mut highs } else { &mut lows }) += 1);
+  }
+  Ok(lows * highs)
+}
+
+// Push the button once, passing every pulse sent to `on_pulse` as (from, to, high)
+fn press<'a>(mods: &mut HashMap<&'a str, Module<'a>>, backrefs: &Backrefs<'a>, mut on_pulse: impl FnMut(Option<&'a str>, &'a str, bool)) {
+  let mut pulses = VecDeque::from([(None, "broadcaster", false)]);
+  while let Some((from, to, hi)) = pulses.pop_front() {
+    on_pulse(from, to, hi);
+    if let Some(module) = mods.get_mut(to) {
+      let sig = match &mut module.logic {
+        ModuleLogic::FlipFlop(mem) => if hi { None } else { *mem = !*mem; Some(*mem) },
+        ModuleLogic::Conjunction(mem) => {
+          // Only modules that send to a conjunction can reach it, so `from` is always set here
+          if let Some(from) = from {
+            if hi { mem.insert(from); } else { mem.remove(from); }
+          }
+          Some(mem.len() != backrefs[to].len())
+        },
+        ModuleLogic::Broadcast => Some(hi),
+      };
+      if let Some(sig) = sig {
+        pulses.extend(module.dests.iter().map(|&dest| (Some(to), dest, sig)));
       }
     }
   }
-
-  lows * highs
 }
 
-fn parse<'a>(lines: impl Iterator<Item=&'a String>) -> (HashMap<&'a str, Module<'a>>, HashMap<&'a str, Vec<&'a str>>) {
-  let mods: HashMap<_, _> = lines
-    .map(|line| parse_module(&line).unwrap().1)
-    .collect();
+// Each module's inputs, by name
+type Backrefs<'a> = HashMap<&'a str, Vec<&'a str>>;
+
+fn parse<'a>(lines: impl Iterator<Item=&'a str>) -> Result<(HashMap<&'a str, Module<'a>>, Backrefs<'a>)> {
+  let mods = lines
+    .enumerate()
+    .map(|(i, line)| parse_all(Solver::DAY, i, line, parse_module))
+    .collect::<Result<HashMap<_, _>>>()?;
 
   let mut backrefs = HashMap::new();
   for (&name, module) in &mods {
     for &dest in &module.dests {
-      (match backrefs.try_insert(dest, vec![]) {
-        Ok(v) => v,
-        Err(e) => e.entry.into_mut(),
-      }).push(name);
+      backrefs.entry(dest).or_insert_with(Vec::new).push(name);
     }
   }
 
-  (mods, backrefs)
+  Ok((mods, backrefs))
 }
 
-pub fn part2(lines: impl Iterator<Item=String>) -> usize {
-  let lines: Vec<_> = lines.collect();
-  let (mut mods, backrefs) = parse(lines.iter());
-
-  /*println!("digraph D {{");
-  println!("rx [shape=square]");
-  for (&name, module) in &mods {
-    println!("{} [shape={}]", name, match module.logic {
-      ModuleLogic::Broadcast => "circle",
-      ModuleLogic::FlipFlop(_) => "diamond",
-      ModuleLogic::Conjunction(_) => "triangle",
-    });
-  }
-  for (&name, module) in &mods {
-    for &dest in &module.dests {
-      println!("{} -> {}", name, dest);
-    }
-  }
-  println!("}}");*/
-
-  let mut pulses = VecDeque::new();
-  let mut presses = 0;
-  let chains = [
-    ("sn", "fh", 0b111000001111, ["ng", "vp", "vf", "th", "qb", "nc", "sd", "nl", "bt", "xd", "tn", "tv"]),
-    ("lr", "ss", 0b100101001111, ["js", "dc", "dp", "xv", "rm", "hj", "bq", "gk", "hm", "rd", "xl", "gx"]),
-    ("tf", "fz", 0b100010110111, ["gr", "cb", "jg", "qn", "td", "zj", "vr", "hq", "kb", "mq", "fl", "gz"]),
-    ("hl", "mf", 0b100011010111, ["lb", "nj", "xx", "hb", "qk", "hs", "fp", "xb", "tl", "kg", "px", "tm"]),
-  ];
-  let mut chain_periods = vec![None; chains.len()];
-  loop {
-    pulses.push_back((None, "broadcaster", false));
-    presses += 1;
-    while let Some((from, to, hi)) = pulses.pop_front() {
-      //if to == "rx" && !hi { break 'presses presses; }
-      if let Some(module) = mods.get_mut(to) {
-        let sig = match &mut module.logic {
-          ModuleLogic::FlipFlop(mem) => if hi { None } else { *mem = !*mem; Some(*mem) },
-          ModuleLogic::Conjunction(mem) => {
-            if hi { mem.insert(from.unwrap()); } else { mem.remove(from.unwrap()); }
-            Some(if mem.len() == backrefs[to].len() { false } else { true })
-          },
-          ModuleLogic::Broadcast => Some(hi),
-        };
-        if let Some(sig) = sig {
-          pulses.extend(module.dests.iter().map(|&dest| (Some(to), dest, sig)));
-        }
+// rx gets a low pulse when the conjunction feeding it has last had a high pulse from every input. As in the real input,
+// each input is taken to send its first high pulse after some number of presses and again every that many presses
+// after, so the answer is the lcm of those numbers
+fn presses_to_rx(text: &str) -> Result<BigUint> {
+  const MAX_PRESSES: usize = 1 << 20;
+  let (mut mods, backrefs) = parse(text.lines())?;
+  let feeder = match backrefs.get("rx").map(Vec::as_slice) {
+    Some(&[feeder]) if matches!(mods[feeder].logic, ModuleLogic::Conjunction(_)) => feeder,
+    _ => return Err(Error::input(Solver::DAY, "part 2 needs rx to be fed by a single conjunction")),
+  };
+
+  let mut periods: HashMap<&str, usize> = HashMap::new();
+  for presses in 1..=MAX_PRESSES {
+    press(&mut mods, &backrefs, |from, to, hi| if hi && to == feeder {
+      if let Some(from) = from {
+        periods.entry(from).or_insert(presses);
       }
+    });
+    if periods.len() == backrefs[feeder].len() {
+      return Ok(periods.into_values().fold(BigUint::one(), |acc, n| acc.lcm(&n.into())));
     }
-
-    for (i, (_, _, _, accs)) in chains.iter().enumerate() {
-      let zero = accs.iter()
-        .map(|&tag| &mods[tag])
-        .all(|module| module.logic == ModuleLogic::FlipFlop(false));
-      if zero && chain_periods[i].is_none() {
-        chain_periods[i] = Some(presses);
-      }
-    }
-    if chain_periods.iter().all(Option::is_some) {
-      break chain_periods.into_iter()
-        .map(Option::unwrap)
-        .reduce(|a, b| lcm(a, b))
-        .unwrap();
-    }
-    //if acc_bits.iter().any(|&b| b == 0) {
-      //dbg!(presses);
-      //for &(cond, inv, read_mask, accs) in &chains {
-        //println!("cond {:?} inv {:?}", mods[cond].logic, mods[inv].logic);
-        //for (_, &acc) in accs.iter().enumerate() {
-          //print!("{}", if let ModuleLogic::FlipFlop(mem) = mods[acc].logic { if mem { '1' } else { '0' } } else { panic!(); });
-        //}
-        //print!(" ");
-        //for (i, &acc) in accs.iter().enumerate() {
-          //let read = (read_mask & 1<<(11-i)) != 0;
-          //print!("{}", if !read { '.' } else if let ModuleLogic::FlipFlop(mem) = mods[acc].logic { if mem { '1' } else { '0' } } else { panic!(); });
-        //}
-        //print!(" ");
-        //for (i, &acc) in accs.iter().enumerate() {
-          //let read = (read_mask & 1<<(11-i)) != 0;
-          //let write = !read || i == 0;
-          //print!("{}", if !write { '.' } else if let ModuleLogic::FlipFlop(mem) = mods[acc].logic { if mem { '1' } else { '0' } } else { panic!(); });
-        //}
-        //println!();
-        //acc_bits.push(accs.iter()
-          //.map(|&tag| &mods[tag])
-          //.enumerate()
-          //.map(|(i, module)| {
-            //let mem = if let ModuleLogic::FlipFlop(mem) = module.logic { mem } else { unreachable!(); };
-            //if mem { 1 << i } else { 0 }
-          //})
-          //.sum());
-      //}
-      //println!();
-    //}
-    //if presses > (1 << 14) { panic!(); }
   }
+  Err(Error::input(Solver::DAY, format!("some inputs to {} sent no high pulse in {} presses", feeder, MAX_PRESSES)))
 }
 
 #[derive(Debug)]
@@ -189,20 +145,26 @@ fn parse_module<'a>(input: &'a str) -> IResult<&'a str, (&'a str, Module<'a>)> {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::test_util::sample_input;
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("20a")), 32000000);
+    assert_eq!(part1(sample_input("20a")), Ok(32000000));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("20")), 787056720);
+    assert_eq!(part1(sample_input("20")), Ok(787056720));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("20")), 212986464842911);
+    assert_eq!(part2(sample_input("20")), Ok(212986464842911u64.into()));
+  }
+
+  #[test]
+  fn test_no_rx_feeder() {
+    let err = part2(sample_input("20a")).unwrap_err();
+    assert_eq!(err.to_string(), "day 20: part 2 needs rx to be fed by a single conjunction");
   }
 }
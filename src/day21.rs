@@ -1,91 +1,107 @@
-use std::collections::{HashMap, VecDeque};
+use std::{collections::{HashMap, VecDeque}, io::BufRead};
 
-use bitvec::prelude::*;
+use crate::{util::{Coord, Grid, TryInsert}, solution::{read_input, joined_lines, self, Solution, Answer, parse_param, unknown_param}, error::{Error, Result}};
 
-pub fn part1(lines: impl Iterator<Item=String>, steps: usize) -> usize {
-  let (map, start) = parse(lines);
+pub fn part1(input: impl BufRead, steps: usize) -> Result<usize> { Ok(reachable(&parse(read_input(input)?.lines())?, steps)) }
+pub fn part2(input: impl BufRead, steps: usize) -> Result<usize> { Ok(reachable(&parse(read_input(input)?.lines())?, steps)) }
+pub fn part1_lines(lines: impl Iterator<Item=String>, steps: usize) -> Result<usize> { part1(joined_lines(lines), steps) }
+pub fn part2_lines(lines: impl Iterator<Item=String>, steps: usize) -> Result<usize> { part2(joined_lines(lines), steps) }
 
-  let mut visited = HashMap::new();
-  let mut to_visit = VecDeque::from([(start, 0)]);
-  while let Some(((y ,x), dist)) = to_visit.pop_front() {
-    if visited.try_insert((y, x), dist).is_ok() && dist < steps {
-      let mut consider = |(y, x)| {
-        let row: &BitVec = &map[y];
-        let tile: bool = row[x];
-        if !tile { to_visit.push_back(((y, x), dist+1)); }
-      };
-      if y > 0 { consider((y-1, x)); }
-      if x > 0 { consider((y, x-1)); }
-      if y < map.len()-1 { consider((y+1, x)); }
-      if x < map[0].len()-1 { consider((y, x+1)); }
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 21;
+  type Input = Garden;
+  type Params = Params;
+
+  fn parse(input: &str) -> Result<Self::Input> { parse(input.lines()) }
+  fn part1(garden: &Self::Input, params: &Params) -> Result<Answer> { Ok(reachable(garden, params.steps).into()) }
+  fn part2(garden: &Self::Input, params: &Params) -> Result<Answer> { Ok(reachable(garden, params.steps).into()) }
+}
+
+pub struct Params {
+  pub steps: usize,
+}
+
+impl Default for Params {
+  fn default() -> Self { Self { steps: 64 } }
+}
+
+impl solution::Params for Params {
+  const NAMES: &'static [&'static str] = &["steps"];
+
+  fn set(&mut self, name: &str, value: &str) -> Result<()> {
+    match name {
+      "steps" => self.steps = parse_param(name, value)?,
+      _ => return unknown_param(name),
     }
+    Ok(())
   }
-  visited.values().filter(|&dist| dist % 2 == 0).count()
 }
 
-pub fn part2(lines: impl Iterator<Item=String>, steps: usize) -> usize {
-  let (map, start) = parse(lines);
+pub struct Garden {
+  map: Grid<bool>,
+  start: Coord,
+}
 
+fn reachable(&Garden { ref map, start }: &Garden, steps: usize) -> usize {
   let mut visited = HashMap::new();
   let mut to_visit = VecDeque::from([(start, 0)]);
-  while let Some(((y ,x), dist)) = to_visit.pop_front() {
-    if visited.try_insert((y, x), dist).is_ok() && dist < steps {
-      let mut consider = |(y, x)| {
-        let row: &BitVec = &map[y];
-        let tile: bool = row[x];
-        if !tile { to_visit.push_back(((y, x), dist+1)); }
-      };
-      if y > 0 { consider((y-1, x)); }
-      if x > 0 { consider((y, x-1)); }
-      if y < map.len()-1 { consider((y+1, x)); }
-      if x < map[0].len()-1 { consider((y, x+1)); }
+  while let Some((pos, dist)) = to_visit.pop_front() {
+    if visited.insert_vacant(pos, dist).is_ok() && dist < steps {
+      for (_, nbr) in map.neighbours(pos) {
+        if !map[nbr] { to_visit.push_back((nbr, dist+1)); }
+      }
     }
   }
   visited.values().filter(|&dist| dist % 2 == 0).count()
 }
 
-type Coord = (usize, usize);
-
-fn parse(lines: impl Iterator<Item=String>) -> (Vec<BitVec>, Coord) {
-  let mut map: Vec<BitVec> = vec![];
-  let mut start = None;
-  for (y, line) in lines.enumerate() {
-    let mut row = bitvec!();
-    for (x, tile) in line.chars().enumerate() {
-      row.push(match tile {
-        '#' => true,
-        '.' => false,
-        'S' => { start = Some((y, x)); false }
-        _ => panic!(),
-      });
-    }
-    map.push(row);
-  }
-  (map, start.unwrap())
+fn parse(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Garden> {
+  let tiles = Grid::parse(Solver::DAY, lines, |c| "#.S".contains(c).then_some(c))?;
+  let starts: Vec<_> = tiles.iter()
+    .filter(|&(_, &c)| c == 'S')
+    .map(|(pos, _)| pos)
+    .take(2)
+    .collect();
+  let start = match starts[..] {
+    [] => return Err(Error::input(Solver::DAY, "no start tile")),
+    [start] => start,
+    [_, other, ..] => return Err(Error::at_tile(Solver::DAY, other, "second start tile")),
+  };
+  Ok(Garden { map: tiles.map(|&c| c == '#'), start })
 }
 
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::test_util::{sample_file, sample_input};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("21a"), 6), 16);
+    assert_eq!(part1(sample_input("21a"), 6), Ok(16));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("21"), 64), 3847);
+    assert_eq!(part1(sample_input("21"), 64), Ok(3847));
+  }
+
+  #[test]
+  fn test_parse_error() {
+    let err = part1(sample_file("21a").replacen('.', "S", 1).as_bytes(), 6).unwrap_err();
+    assert_eq!(err.to_string(), "day 21, line 6, column 6: second start tile");
+    let err = part1(sample_file("21a").replace('S', ".").as_bytes(), 6).unwrap_err();
+    assert_eq!(err.to_string(), "day 21: no start tile");
   }
 
   /*#[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("21a"), 6), 16);
+    assert_eq!(part2(sample_input("21a"), 6), Ok(16));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("21")), 212986464842911);
+    assert_eq!(part2(sample_input("21")), Ok(212986464842911));
   }*/
 }
@@ -1,12 +1,28 @@
-use std::{iter::once, collections::{HashMap, HashSet}, mem::swap, cmp::Ordering};
+use std::{iter::once, collections::{HashMap, HashSet}, mem::swap, cmp::Ordering, io::BufRead};
 
-use crate::util::usize;
+use crate::{util::usize, solution::{read_input, joined_lines, Solution, Answer}, error::{Result, parse_lines}};
 
 use itertools::Itertools;
 use nom::{IResult, character::complete::char, multi::separated_list1, combinator::{map_res, map}, sequence::separated_pair};
 
-pub fn part1(lines: impl Iterator<Item=String>) -> usize {
-  let mut state = State::parse(lines);
+pub fn part1(input: impl BufRead) -> Result<usize> { Ok(count_disintegrable(State::parse(read_input(input)?.lines())?)) }
+pub fn part2(input: impl BufRead) -> Result<usize> { Ok(sum_chain_reactions(State::parse(read_input(input)?.lines())?)) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part2(joined_lines(lines)) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 22;
+  type Input = State;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> { State::parse(input.lines()) }
+  fn part1(state: &Self::Input, _: &()) -> Result<Answer> { Ok(count_disintegrable(state.clone()).into()) }
+  fn part2(state: &Self::Input, _: &()) -> Result<Answer> { Ok(sum_chain_reactions(state.clone()).into()) }
+}
+
+fn count_disintegrable(mut state: State) -> usize {
   state.settle();
   state.bricks.iter()
     .filter(|&brick|
@@ -14,8 +30,7 @@ pub fn part1(lines: impl Iterator<Item=String>) -> usize {
     .count()
 }
 
-pub fn part2(lines: impl Iterator<Item=String>) -> usize {
-  let mut state = State::parse(lines);
+fn sum_chain_reactions(mut state: State) -> usize {
   state.settle();
   (0..state.bricks.len())
     .map(|i| {
@@ -29,23 +44,20 @@ pub fn part2(lines: impl Iterator<Item=String>) -> usize {
 type Coord = [usize; 3];
 
 #[derive(Clone)]
-struct State {
+pub struct State {
   bricks: Vec<Brick>,
   cols: HashMap<[usize; 2], Vec<usize>>,
   unsupported: HashSet<usize>,
 }
 
 impl State {
-  fn parse(lines: impl Iterator<Item=String>) -> Self {
-    let mut bricks: Vec<_> = lines.map(|line| Brick::parse(&line).unwrap().1).collect();
+  fn parse(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Self> {
+    let mut bricks = parse_lines(Solver::DAY, lines, Brick::parse)?;
 
     let mut cols = HashMap::new();
     for (i, brick) in bricks.iter().enumerate() {
       for xz in brick.iter_xys() {
-        (match cols.try_insert(xz, vec![]) {
-          Ok(v) => v,
-          Err(e) => e.entry.into_mut(),
-        }).push(i);
+        cols.entry(xz).or_insert_with(Vec::new).push(i);
       }
     }
     for col in cols.values_mut() {
@@ -63,7 +75,7 @@ impl State {
     }
     let unsupported: HashSet<_> = bricks.iter().positions(Brick::can_fall).collect();
     
-    Self { bricks, cols, unsupported }
+    Ok(Self { bricks, cols, unsupported })
   }
 
   fn settle(&mut self) -> HashSet<usize> {
@@ -207,25 +219,25 @@ fn parse_coord(input: &str) -> IResult<&str, Coord> {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::test_util::sample_input;
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("22a")), 5);
+    assert_eq!(part1(sample_input("22a")), Ok(5));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("22")), 522);
+    assert_eq!(part1(sample_input("22")), Ok(522));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("22a")), 7);
+    assert_eq!(part2(sample_input("22a")), Ok(7));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("22")), 83519);
+    assert_eq!(part2(sample_input("22")), Ok(83519));
   }
 }
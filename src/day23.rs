@@ -1,47 +1,67 @@
-use std::{collections::{HashSet, HashMap}, mem::take, iter::once};
+use std::{collections::{HashSet, HashMap}, mem::take, iter::once, io::BufRead};
 
 use arrayvec::ArrayVec;
 use bit_set::BitSet;
 use itertools::Itertools;
-use strum::{EnumIter, IntoEnumIterator};
-
-pub fn part1(lines: impl Iterator<Item=String>) -> usize { process(lines, false) }
-pub fn part2(lines: impl Iterator<Item=String>) -> usize { process(lines, true) }
-
-fn process(lines: impl Iterator<Item=String>, ignore_slopes: bool) -> usize {
-  let map: Vec<Vec<_>> = lines
-    .map(|line| line.chars()
-      .map(|c| match c {
-        '.' => Tile::Path,
-        '#' => Tile::Forest,
-        '>' => Tile::Slope(Dir::E),
-        '^' => Tile::Slope(Dir::N),
-        '<' => Tile::Slope(Dir::W),
-        'v' => Tile::Slope(Dir::S),
-        _ => panic!(),
-      })
-      .collect())
-    .collect();
-  let bounds = (map.len(), map[0].len());
+use strum::IntoEnumIterator;
+
+use crate::{util::{Dir, Grid, TryInsert}, solution::{read_input, joined_lines, Solution, Answer}, error::{Error, Result}};
+
+pub fn part1(input: impl BufRead) -> Result<usize> { Ok(process(&parse(read_input(input)?.lines())?, false)) }
+pub fn part2(input: impl BufRead) -> Result<usize> { Ok(process(&parse(read_input(input)?.lines())?, true)) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part1(joined_lines(lines)) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part2(joined_lines(lines)) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 23;
+  type Input = Trails;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> { parse(input.lines()) }
+  fn part1(trails: &Self::Input, _: &()) -> Result<Answer> { Ok(process(trails, false).into()) }
+  fn part2(trails: &Self::Input, _: &()) -> Result<Answer> { Ok(process(trails, true).into()) }
+}
 
+pub struct Trails(Grid<Tile>);
+
+fn parse(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Trails> {
+  let map = Grid::parse(Solver::DAY, lines, |c| match c {
+    '.' => Some(Tile::Path),
+    '#' => Some(Tile::Forest),
+    '>' => Some(Tile::Slope(Dir::E)),
+    '^' => Some(Tile::Slope(Dir::N)),
+    '<' => Some(Tile::Slope(Dir::W)),
+    'v' => Some(Tile::Slope(Dir::S)),
+    _ => None,
+  })?;
+  // The start and end are the paths in the first and last rows
+  for (y, edge) in [(0, "first"), (map.height() - 1, "last")] {
+    if !map.row(y).contains(&Tile::Path) {
+      return Err(Error::input(Solver::DAY, format!("no path in the {} row", edge)));
+    }
+  }
+  Ok(Trails(map))
+}
+
+fn process(Trails(map): &Trails, ignore_slopes: bool) -> usize {
   let start = (
     0,
-    map[0].iter().position(|&t| t == Tile::Path).unwrap(),
+    map.row(0).iter().position(|&t| t == Tile::Path).unwrap(),
   );
   let end = (
-    map.len() - 1,
-    map[map.len() - 1].iter().position(|&t| t == Tile::Path).unwrap(),
+    map.height() - 1,
+    map.row(map.height() - 1).iter().position(|&t| t == Tile::Path).unwrap(),
   );
 
   let mut nodes = vec![];
   let mut lookup = HashMap::new();
 
-  for (y, row) in map.iter().enumerate() {
-    for (x, &tile) in row.iter().enumerate() {
-      if tile != Tile::Forest {
-        lookup.insert((y, x), nodes.len());
-        nodes.push(Node::default());
-      }
+  for (pos, &tile) in map.iter() {
+    if tile != Tile::Forest {
+      lookup.insert(pos, nodes.len());
+      nodes.push(Node::default());
     }
   }
 
@@ -49,8 +69,8 @@ fn process(lines: impl Iterator<Item=String>, ignore_slopes: bool) -> usize {
   let end = lookup[&end];
 
   for (&pos, &node) in &lookup {
-    for dir in map[pos.0][pos.1].possible_exits(ignore_slopes) {
-      if let Some(nbr) = dir.try_step(pos, bounds) && map[nbr.0][nbr.1] != Tile::Forest {
+    for dir in map[pos].possible_exits(ignore_slopes) {
+      if let Some(nbr) = map.step(pos, dir).filter(|&nbr| map[nbr] != Tile::Forest) {
         let nbr_node = lookup[&nbr];
         nodes[node].outs.insert(nbr_node, 1);
         nodes[nbr_node].ins.insert(node);
@@ -85,9 +105,9 @@ fn process(lines: impl Iterator<Item=String>, ignore_slopes: bool) -> usize {
             to_fix.push(nbr);
           } else {
             // We can discover multiple paths. For this, we only need the longest.
-            if let Err(mut e) = nodes[nbr].outs.try_insert(next, d0 + d1) {
-              if e.value > *e.entry.get() {
-                e.entry.insert(e.value);
+            if let Err((old, dist)) = nodes[nbr].outs.insert_vacant(next, d0 + d1) {
+              if dist > *old {
+                *old = dist;
               }
               debug_assert!(nodes[next].ins.contains(&nbr));
             } else {
@@ -98,33 +118,29 @@ fn process(lines: impl Iterator<Item=String>, ignore_slopes: bool) -> usize {
         }
         true
       },
-      2 => {
-        if nodes[node].ins.len() == 2 && nodes[node].outs.keys().all(|&out| nodes[node].ins.contains(&out)) {
-          // Simplify corridor
-          let mut iter = take(&mut nodes[node].outs).into_iter();
-          let ((nbr0, dn0), (nbr1, dn1)) = iter.next_tuple().unwrap();
-          debug_assert!(iter.is_empty());
-
-          let d0n = nodes[nbr0].outs.remove(&node).unwrap();
-          let ins = nodes[nbr0].outs.insert(nbr1, d0n + dn1);
-          debug_assert_eq!(ins, None);
-          let rem = nodes[nbr0].ins.remove(&node);
-          debug_assert!(rem);
-          let ins = nodes[nbr0].ins.insert(nbr1);
-          debug_assert!(ins);
-
-          let d1n = nodes[nbr1].outs.remove(&node).unwrap();
-          let ins = nodes[nbr1].outs.insert(nbr0, d1n + dn0);
-          debug_assert_eq!(ins, None);
-          let rem = nodes[nbr1].ins.remove(&node);
-          debug_assert!(rem);
-          let ins = nodes[nbr1].ins.insert(nbr0);
-          debug_assert!(ins);
-
-          true
-        } else {
-          false
-        }
+      2 if nodes[node].ins.len() == 2 && nodes[node].outs.keys().all(|&out| nodes[node].ins.contains(&out)) => {
+        // Simplify corridor
+        let mut iter = take(&mut nodes[node].outs).into_iter();
+        let ((nbr0, dn0), (nbr1, dn1)) = iter.next_tuple().unwrap();
+        debug_assert_eq!(iter.len(), 0);
+
+        let d0n = nodes[nbr0].outs.remove(&node).unwrap();
+        let ins = nodes[nbr0].outs.insert(nbr1, d0n + dn1);
+        debug_assert_eq!(ins, None);
+        let rem = nodes[nbr0].ins.remove(&node);
+        debug_assert!(rem);
+        let ins = nodes[nbr0].ins.insert(nbr1);
+        debug_assert!(ins);
+
+        let d1n = nodes[nbr1].outs.remove(&node).unwrap();
+        let ins = nodes[nbr1].outs.insert(nbr0, d1n + dn0);
+        debug_assert_eq!(ins, None);
+        let rem = nodes[nbr1].ins.remove(&node);
+        debug_assert!(rem);
+        let ins = nodes[nbr1].ins.insert(nbr0);
+        debug_assert!(ins);
+
+        true
       },
       _ => false,
     } {
@@ -173,8 +189,6 @@ struct Node {
   outs: HashMap<usize, usize>,
 }
 
-type Coord = (usize, usize);
-
 #[derive(Clone, Copy, PartialEq, Eq)]
 enum Tile {
   Path,
@@ -184,24 +198,9 @@ enum Tile {
 
 impl Tile {
   fn possible_exits(&self, ignore_slopes: bool) -> Vec<Dir> {
-    if !ignore_slopes && let &Tile::Slope(dir) = self {
-      vec![dir]
-    } else {
-      Dir::iter().collect()
-    }
-  }
-}
-
-#[derive(Clone, Copy, EnumIter, PartialEq, Eq)]
-enum Dir { E, N, W, S }
-
-impl Dir {
-  fn try_step(&self, (y, x): Coord, (max_y, max_x): Coord) -> Option<Coord> {
     match self {
-      Self::E => x.checked_add(1).map(|x| (y, x)).filter(|&(_, x)| x < max_x),
-      Self::N => y.checked_sub(1).map(|y| (y, x)),
-      Self::W => x.checked_sub(1).map(|x| (y, x)),
-      Self::S => y.checked_add(1).map(|y| (y, x)).filter(|&(y, _)| y < max_y),
+      &Tile::Slope(dir) if !ignore_slopes => vec![dir],
+      _ => Dir::iter().collect(),
     }
   }
 }
@@ -209,25 +208,37 @@ impl Dir {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::test_util::{sample_file, sample_input};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("23a")), 94);
+    assert_eq!(part1(sample_input("23a")), Ok(94));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("23")), 2178);
+    assert_eq!(part1(sample_input("23")), Ok(2178));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("23a")), 154);
+    assert_eq!(part2(sample_input("23a")), Ok(154));
   }
 
   #[test]
   fn test2() {
-    assert_eq!(part2(sample_lines("23")), 6486);
+    assert_eq!(part2(sample_input("23")), Ok(6486));
+  }
+
+  #[test]
+  fn test_parse_error() {
+    let err = part1(sample_file("23a").replacen("#.", "#o", 1).as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 23, line 1, column 2: unexpected tile at \"o###################\"");
+    let err = part1(sample_file("23a").replace('.', "#").as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 23: no path in the first row");
+    let file = sample_file("23a");
+    let last_row = file.trim_end().rsplit('\n').next().unwrap();
+    let err = part1(file.replace(last_row, &"#".repeat(last_row.len())).as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 23: no path in the last row");
   }
 }
@@ -1,16 +1,60 @@
-use std::{fmt::Debug, iter::zip, mem::{swap, replace, take}};
+use std::{fmt::Debug, iter::zip, mem::{replace, take}, io::BufRead};
 
-use crate::util::isize;
+use crate::{util::isize, solution::{read_input, joined_lines, self, Solution, Answer, parse_param, unknown_param}, error::{self, Error, Result}};
 
 use arrayvec::ArrayVec;
 use itertools::Itertools;
 use nom::{IResult, character::complete::char, multi::{separated_list1, many0}, combinator::{map_res, eof}, sequence::{separated_pair, terminated, preceded}, bytes::complete::tag};
 use num::{BigInt, Signed, Zero, BigRational, One};
 
-pub fn part1(lines: impl Iterator<Item=String>, test_range: (isize, isize)) -> usize {
-  let hailstones: Vec<_> = lines
-    .map(|line| parse(&line).unwrap().1)
-    .map(|(pos, vel)| (flatten(pos), flatten(vel)))
+pub fn part1(input: impl BufRead, test_range: (isize, isize)) -> Result<usize> { Ok(count_crossings(&parse_lines(read_input(input)?.lines())?, test_range)) }
+pub fn part2(input: impl BufRead) -> Result<usize> { rock_sum(&parse_lines(read_input(input)?.lines())?) }
+pub fn part1_lines(lines: impl Iterator<Item=String>, test_range: (isize, isize)) -> Result<usize> { part1(joined_lines(lines), test_range) }
+pub fn part2_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part2(joined_lines(lines)) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 24;
+  type Input = Vec<(Coord, Coord)>;
+  type Params = Params;
+
+  fn parse(input: &str) -> Result<Self::Input> { parse_lines(input.lines()) }
+  fn part1(hailstones: &Self::Input, params: &Params) -> Result<Answer> { Ok(count_crossings(hailstones, params.test_range).into()) }
+  fn part2(hailstones: &Self::Input, _: &Params) -> Result<Answer> { rock_sum(hailstones).map(Answer::from) }
+}
+
+pub struct Params {
+  pub test_range: (isize, isize),
+}
+
+impl Default for Params {
+  fn default() -> Self { Self { test_range: (200000000000000, 400000000000000) } }
+}
+
+impl solution::Params for Params {
+  const NAMES: &'static [&'static str] = &["test_range"];
+
+  fn set(&mut self, name: &str, value: &str) -> Result<()> {
+    match name {
+      "test_range" => {
+        let (lo, hi) = value.split_once(',')
+          .ok_or_else(|| Error::Usage(format!("expected min,max for parameter {:?}", name)))?;
+        self.test_range = (parse_param(name, lo.trim())?, parse_param(name, hi.trim())?);
+      },
+      _ => return unknown_param(name),
+    }
+    Ok(())
+  }
+}
+
+fn parse_lines(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Vec<(Coord, Coord)>> {
+  error::parse_lines(Solver::DAY, lines, parse)
+}
+
+fn count_crossings(hailstones: &[(Coord, Coord)], test_range: (isize, isize)) -> usize {
+  let hailstones: Vec<_> = hailstones.iter()
+    .map(|&(pos, vel)| (flatten(pos), flatten(vel)))
     .collect();
 
   let test_range = (
@@ -50,10 +94,10 @@ fn flatten<T: Copy>(xyz: [T; 3]) -> [T; 2] {
   [xyz[0], xyz[1]]
 }
 
-pub fn part2(lines: impl Iterator<Item=String>) -> usize {
-  let hailstones: Vec<_> = lines
-    .map(|line| parse(&line).unwrap().1)
-    .collect();
+// The search tries each whole time the rock could hit hailstone 1 at, up to this
+const MAX_HIT_TIME: isize = 10_000;
+
+fn rock_sum(hailstones: &[(Coord, Coord)]) -> Result<usize> {
 
   /*let exec = |t0, t1| {
     let h0 = op2(&h0_pos, &h0_vel, |p, v| p + t0*v);
@@ -77,13 +121,11 @@ pub fn part2(lines: impl Iterator<Item=String>) -> usize {
     Some(rp)
   };*/
 
-  let (h0_pos, h0_vel) = hailstones[0];
-  let (h1_pos, h1_vel) = hailstones[1];
-  let (h2_pos, h2_vel) = hailstones[2];
+  let &[(h0_pos, h0_vel), (h1_pos, h1_vel), (h2_pos, h2_vel), ..] = hailstones else {
+    return Err(Error::input(Solver::DAY, "part 2 needs at least three hailstones"));
+  };
   // We are given that hailstone paths do not intersect. Assume they are not coplanar, either
-  let r_pos = (0..).find_map(|t1| {
-    if t1 % 1000 == 0 { println!("{}", t1); }
-
+  let r_pos = (0..=MAX_HIT_TIME).find_map(|t1| {
     // Find intersection of H2 with the plane formed by H0 and H1(t1)
     // P0 + a*V0 + b*(P1 + t1*V1 - P0) = P2 + t2*V2
     // a*V0 + b*(P1 + t1*V1 - P0) - t2*V2 = P2 - P0
@@ -101,7 +143,8 @@ pub fn part2(lines: impl Iterator<Item=String>) -> usize {
       Some(mut sol) => take(&mut sol[2]),
       None => return None,
     };
-    if t2.is_negative() { return None; }
+    // Hitting two hailstones at once in different places would take an infinite velocity
+    if t2.is_negative() || t2 == BigInt::from(t1).into() { return None; }
 
     let h2_hit = op2(h2_pos, h2_vel, |p, v| &t2*BigInt::from(v) + BigInt::from(p));
 
@@ -130,38 +173,21 @@ pub fn part2(lines: impl Iterator<Item=String>) -> usize {
       }
     }
     Some(r_pos)
-  }).unwrap();
+  }).ok_or_else(|| Error::input(Solver::DAY, format!("no rock hits hailstone 1 by time {} and then every other one", MAX_HIT_TIME)))?;
   let res: BigRational = r_pos.into_iter().sum();
-  assert!(res.is_integer());
-  res.to_integer().try_into().unwrap()
-}
-
-fn cross(a: [BigRational; 3], b: [BigRational; 3]) -> [BigRational; 3] {
-  [
-    &a[1]*&b[2] - &a[2]*&b[1],
-    &a[2]*&b[0] - &a[0]*&b[2],
-    &a[0]*&b[1] - &a[1]*&b[0],
-  ]
-}
-fn dot(a: [BigRational; 3], b: [BigRational; 3]) -> BigRational {
-  zip(a, b).map(|(a, b)| a * b).sum()
-}
-
-fn debug_print(mat_rows: &[[BigRational; 3]; 3], y: &[BigRational; 3]) {
-  for i in 0..3 {
-    print!("[");
-    for j in 0..3 {
-      print!("{}, ", mat_rows[i][j]);
-    }
-    println!("] = [{}]", y[i]);
+  if !res.is_integer() {
+    return Err(Error::input(Solver::DAY, format!("the rock's coordinates sum to {}, which is not a whole number", res)));
   }
-  println!();
+  res.to_integer().try_into()
+    .map_err(|_| Error::input(Solver::DAY, format!("the rock's coordinates sum to {}, which is not a count", res)))
 }
+
+// Gauss-Jordan elimination. Indexing reads more clearly than iterators here
+#[allow(clippy::needless_range_loop)]
 fn solve_mx_eq_y(mut mat_rows: [[BigRational; 3]; 3], mut y: [BigRational; 3]) -> Option<[BigRational; 3]> {
   for i in 0..3 {
-    //debug_print(&mat_rows, &y);
     {
-      let row = i + mat_rows[i..].into_iter().position(|row| !row[i].is_zero())?;
+      let row = i + mat_rows[i..].iter().position(|row| !row[i].is_zero())?;
       mat_rows.swap(i, row);
       y.swap(i, row);
     }
@@ -184,7 +210,6 @@ fn solve_mx_eq_y(mut mat_rows: [[BigRational; 3]; 3], mut y: [BigRational; 3]) -
       }
     }
   }
-  //debug_print(&mat_rows, &y);
   debug_assert!(mat_rows.into_iter()
     .enumerate()
     .all(|(i, row)| row.into_iter()
@@ -195,13 +220,6 @@ fn solve_mx_eq_y(mut mat_rows: [[BigRational; 3]; 3], mut y: [BigRational; 3]) -
 
 type Coord = [isize; 3];
 
-fn op1<I, O: Debug>(a: [I; 3], mut f: impl FnMut(I) -> O) -> [O; 3] {
-  let mut x = ArrayVec::new();
-  for a in a {
-    x.push(f(a));
-  }
-  x.into_inner().unwrap()
-}
 fn op2<I0, I1, O: Debug>(a: [I0; 3], b: [I1; 3], mut f: impl FnMut(I0, I1) -> O) -> [O; 3] {
   let mut x = ArrayVec::new();
   for (a, b) in zip(a, b) {
@@ -234,25 +252,38 @@ fn parse_coord(input: &str) -> IResult<&str, Coord> {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::test_util::{sample_file, sample_input};
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("24a"), (7, 27)), 2);
+    assert_eq!(part1(sample_input("24a"), (7, 27)), Ok(2));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("24"), (200000000000000, 400000000000000)), 14672);
+    assert_eq!(part1(sample_input("24"), (200000000000000, 400000000000000)), Ok(14672));
   }
 
   #[test]
   fn test2_sample() {
-    assert_eq!(part2(sample_lines("24a")), 47);
+    assert_eq!(part2(sample_input("24a")), Ok(47));
   }
 
   #[test]
-  fn test2() {
-    assert_eq!(part2(sample_lines("24")), 6486);
+  fn test2_errors() {
+    let err = part2("19, 13, 30 @ -2, 1, -2\n18, 19, 22 @ -1, -1, -2\n".as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 24: part 2 needs at least three hailstones");
+    let err = part2("0, 0, 0 @ 0, 1, 0\n1, 0, 0 @ 0, 1, 0\n3, 0, 0 @ 0, 2, 0\n0, 0, 1 @ 1, 0, 0\n".as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 24: no rock hits hailstone 1 by time 10000 and then every other one");
+    // The sample moved 100 back along every axis, so the rock starts at (-76, -87, -90)
+    let shifted: String = sample_file("24a").lines()
+      .map(|line| {
+        let (pos, vel) = line.split_once(" @ ").unwrap();
+        let pos: Vec<_> = pos.split(", ").map(|n| (n.trim().parse::<isize>().unwrap() - 100).to_string()).collect();
+        format!("{} @ {}\n", pos.join(", "), vel)
+      })
+      .collect();
+    let err = part2(shifted.as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 24: the rock's coordinates sum to -253, which is not a count");
   }
 }
@@ -1,19 +1,38 @@
 
-use std::collections::{BTreeSet, HashSet, HashMap, BTreeMap};
+use std::{collections::{BTreeSet, HashSet, HashMap, BTreeMap}, io::BufRead};
 
 use arrayvec::ArrayVec;
 use itertools::{Itertools, iproduct};
 use nom::{IResult, character::complete::{char, alpha1}, multi::separated_list1, combinator::eof, sequence::{separated_pair, terminated}, bytes::complete::tag};
 
-pub fn part1(lines: impl Iterator<Item=String>) -> usize {
-  let mut wires = HashMap::<_, HashSet<_>>::new();
+use crate::{solution::{read_input, joined_lines, Solution, Answer}, error::{Error, Result, parse_all}};
+
+pub fn part1(input: impl BufRead) -> Result<usize> { cut_product(&parse_wires(read_input(input)?.lines())?) }
+pub fn part1_lines(lines: impl Iterator<Item=String>) -> Result<usize> { part1(joined_lines(lines)) }
+
+pub struct Solver;
+
+impl Solution for Solver {
+  const DAY: u8 = 25;
+  const PARTS: u8 = 1;
+  type Input = Wires;
+  type Params = ();
+
+  fn parse(input: &str) -> Result<Self::Input> { parse_wires(input.lines()) }
+  fn part1(wires: &Self::Input, _: &()) -> Result<Answer> { cut_product(wires).map(Answer::from) }
+}
+
+type Wires = HashMap<usize, HashSet<usize>>;
+
+fn parse_wires(lines: impl Iterator<Item=impl AsRef<str>>) -> Result<Wires> {
+  let mut wires = Wires::new();
   let mut translation = HashMap::new();
   let mut translate = |string: &str| {
     let next_id = translation.len();
     *translation.entry(string.to_string()).or_insert(next_id)
   };
-  for line in lines {
-    let (a, bs) = parse(&line).unwrap().1;
+  for (i, line) in lines.enumerate() {
+    let (a, bs) = parse_all(Solver::DAY, i, line.as_ref(), parse)?;
     let a = translate(a);
     for b in bs {
       let b = translate(b);
@@ -22,21 +41,27 @@ pub fn part1(lines: impl Iterator<Item=String>) -> usize {
       }
     }
   }
+  Ok(wires)
+}
 
+fn cut_product(wires: &Wires) -> Result<usize> {
+  let no_cut = || Error::input(Solver::DAY, "no three wires split the components in two");
   let mut nodes_iter = wires.keys();
-  let start = *nodes_iter.next().unwrap();
-  let paths: ArrayVec<Vec<_>, 3> = nodes_iter
-    .find_map(|&end| {
-      let mut pathfinder = IndependentPaths { wires: &wires, used: HashSet::new() };
-      let mut paths = ArrayVec::<_, 3>::new();
-      for _ in 0..3 {
-        paths.push(pathfinder.try_block_path(start, end).expect("at least three independent paths should exist"));
-      }
-      if pathfinder.try_find_path(start, end).is_some() { return None; }
-
-      Some(paths)
-    })
-    .unwrap()
+  let start = *nodes_iter.next().ok_or_else(|| Error::input(Solver::DAY, "no components"))?;
+  let mut cut_paths = None;
+  for &end in nodes_iter {
+    let mut pathfinder = IndependentPaths { wires, used: HashSet::new() };
+    let mut paths = ArrayVec::<_, 3>::new();
+    for _ in 0..3 {
+      paths.push(pathfinder.try_block_path(start, end)
+        .ok_or_else(|| Error::input(Solver::DAY, "fewer than three wires split the components in two"))?);
+    }
+    if pathfinder.try_find_path(start, end).is_none() {
+      cut_paths = Some(paths);
+      break;
+    }
+  }
+  let paths: ArrayVec<Vec<_>, 3> = cut_paths.ok_or_else(no_cut)?
     .into_iter()
     .map(|path| path.into_iter()
       .tuple_windows()
@@ -72,7 +97,7 @@ pub fn part1(lines: impl Iterator<Item=String>) -> usize {
         None
       }
     })
-    .unwrap()
+    .ok_or_else(no_cut)
 }
 
 fn parse(input: &str) -> IResult<&str, (&str, Vec<&str>)> {
@@ -87,7 +112,7 @@ fn parse(input: &str) -> IResult<&str, (&str, Vec<&str>)> {
 }
 
 struct IndependentPaths<'a> {
-  wires: &'a HashMap<usize, HashSet<usize>>,
+  wires: &'a Wires,
   used: HashSet<(usize, usize)>,
 }
 
@@ -128,15 +153,24 @@ fn canonical_edge((a, b): (usize, usize)) -> (usize, usize) {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::test_util::sample_lines;
+  use crate::test_util::sample_input;
 
   #[test]
   fn test1_sample() {
-    assert_eq!(part1(sample_lines("25a")), 54);
+    assert_eq!(part1(sample_input("25a")), Ok(54));
   }
 
   #[test]
   fn test1() {
-    assert_eq!(part1(sample_lines("25")), 598120);
+    assert_eq!(part1(sample_input("25")), Ok(598120));
+  }
+
+  #[test]
+  fn test_no_cut() {
+    assert_eq!(part1("".as_bytes()).unwrap_err().to_string(), "day 25: no components");
+    let err = part1("a: b\n".as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 25: fewer than three wires split the components in two");
+    let err = part1("a: b c d e\nb: c d e\nc: d e\nd: e\n".as_bytes()).unwrap_err();
+    assert_eq!(err.to_string(), "day 25: no three wires split the components in two");
   }
 }
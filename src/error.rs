@@ -0,0 +1,120 @@
+use std::{fmt::{self, Display}, io};
+
+use nom::{IResult, Offset, combinator::all_consuming};
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub enum Error {
+  Parse(ParseError),
+  Usage(String),
+  Manifest(String),
+  Io(String),
+  Overflow(String),
+  // Input that parses but makes no sense as a whole, so there is no one place to point at
+  Input(u8, String),
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError {
+  pub day: u8,
+  pub line: usize,
+  pub column: usize,
+  pub snippet: String,
+  pub reason: String,
+}
+
+const SNIPPET_LEN: usize = 20;
+
+impl Error {
+  // `text` starts at (zero-based) line `line_idx` of the input, and the error is `offset` bytes into it
+  pub(crate) fn parse(day: u8, line_idx: usize, text: &str, offset: usize, reason: impl Into<String>) -> Self {
+    let before = &text[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Self::Parse(ParseError {
+      day,
+      line: line_idx + before.matches('\n').count() + 1,
+      column: before[line_start..].chars().count() + 1,
+      snippet: text[offset..].lines().next().unwrap_or_default().chars().take(SNIPPET_LEN).collect(),
+      reason: reason.into(),
+    })
+  }
+
+  // A tile of a grid, at zero-based row and column
+  pub(crate) fn at_tile(day: u8, (y, x): (usize, usize), reason: impl Into<String>) -> Self {
+    Self::Parse(ParseError { day, line: y + 1, column: x + 1, snippet: String::new(), reason: reason.into() })
+  }
+
+  pub(crate) fn input(day: u8, reason: impl Into<String>) -> Self {
+    Self::Input(day, reason.into())
+  }
+}
+
+impl Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Parse(e) => e.fmt(f),
+      Self::Usage(msg) | Self::Manifest(msg) => f.write_str(msg),
+      Self::Io(msg) => write!(f, "reading input: {}", msg),
+      Self::Overflow(n) => write!(f, "answer {} does not fit in 128 bits", n),
+      Self::Input(day, reason) => write!(f, "day {}: {}", day, reason),
+    }
+  }
+}
+
+impl Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "day {}, line {}, column {}: {}", self.day, self.line, self.column, self.reason)?;
+    if !self.snippet.is_empty() {
+      write!(f, " at {:?}", self.snippet)?;
+    }
+    Ok(())
+  }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+  fn from(e: io::Error) -> Self {
+    Self::Io(e.to_string())
+  }
+}
+
+// Run a nom parser over the whole of `text`, which starts at (zero-based) line `line_idx` of the input
+pub(crate) fn parse_all<'a, T>(day: u8, line_idx: usize, text: &'a str, parser: impl FnMut(&'a str) -> IResult<&'a str, T>) -> Result<T> {
+  all_consuming(parser)(text)
+    .map(|(_, t)| t)
+    .map_err(|e| match e {
+      nom::Err::Error(e) | nom::Err::Failure(e) =>
+        Error::parse(day, line_idx, text, text.offset(e.input), format!("unexpected input ({})", e.code.description())),
+      nom::Err::Incomplete(_) => Error::parse(day, line_idx, text, text.len(), "incomplete input"),
+    })
+}
+
+pub(crate) fn parse_lines<T>(day: u8, lines: impl Iterator<Item=impl AsRef<str>>, mut parser: impl FnMut(&str) -> IResult<&str, T>) -> Result<Vec<T>> {
+  lines
+    .enumerate()
+    .map(|(i, line)| parse_all(day, i, line.as_ref(), &mut parser))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::util::usize;
+  use nom::{bytes::complete::tag, sequence::pair};
+
+  #[test]
+  fn test_parse_all() {
+    let err = parse_all(3, 0, "abc\nabd\n", |input| pair(tag("abc\n"), tag("abc\n"))(input)).unwrap_err();
+    assert_eq!(err, Error::Parse(ParseError { day: 3, line: 2, column: 1, snippet: "abd".to_string(), reason: "unexpected input (Tag)".to_string() }));
+    assert_eq!(err.to_string(), "day 3, line 2, column 1: unexpected input (Tag) at \"abd\"");
+  }
+
+  #[test]
+  fn test_parse_lines() {
+    assert_eq!(parse_lines(1, ["12", "34"].into_iter(), usize), Ok(vec![12, 34]));
+    let err = parse_lines(1, ["12", "3x4"].into_iter(), usize).unwrap_err();
+    assert_eq!(err.to_string(), "day 1, line 2, column 2: unexpected input (End of file) at \"x4\"");
+  }
+}
@@ -0,0 +1,346 @@
+use std::{collections::{BTreeMap, BTreeSet, HashSet}, fmt::Write};
+
+use itertools::Itertools;
+use num::{BigUint, Integer, One};
+use rand::{Rng, SeedableRng, rngs::StdRng, seq::SliceRandom};
+
+use crate::{solution::Answer, error::Result};
+
+// Random puzzle inputs for stress and property tests. The same size and seed always give the same input
+
+pub fn generate(day: u8, size: usize, seed: u64) -> Option<String> {
+  match day {
+    4 => Some(day04(size, seed)),
+    5 => Some(day05(size, seed)),
+    8 => Some(day08(size, seed).0),
+    19 => Some(day19(size, seed)),
+    20 => Some(day20(size, seed).0),
+    22 => Some(day22(size, seed)),
+    24 => Some(day24(size, seed).0),
+    25 => Some(day25(size, seed).0),
+    _ => None,
+  }
+}
+
+// `size` scratchcards, most winning five or more of the following cards so that the copy counts grow exponentially
+pub fn day04(size: usize, seed: u64) -> String {
+  let mut rng = StdRng::seed_from_u64(seed);
+  let mut input = String::new();
+  for id in 1..=size.max(1) {
+    let mut pool: Vec<usize> = (1..100).collect();
+    pool.shuffle(&mut rng);
+    let (winners, others) = pool.split_at(10);
+    let matches = rng.gen_range(3..=10);
+    let mut nums: Vec<_> = winners[..matches].iter().chain(&others[..25 - matches]).collect();
+    nums.shuffle(&mut rng);
+    write!(input, "Card {:>3}:", id).unwrap();
+    for n in winners {
+      write!(input, " {:>2}", n).unwrap();
+    }
+    input.push_str(" |");
+    for n in nums {
+      write!(input, " {:>2}", n).unwrap();
+    }
+    input.push('\n');
+  }
+  input
+}
+
+// `size` seed ranges, then seven maps of `size` disjoint source ranges each
+pub fn day05(size: usize, seed: u64) -> String {
+  const LIMIT: usize = 1 << 32;
+  let mut rng = StdRng::seed_from_u64(seed);
+  let mut input = String::from("seeds:");
+  for _ in 0..size.max(1) {
+    write!(input, " {} {}", rng.gen_range(0..LIMIT), rng.gen_range(1..LIMIT >> 8)).unwrap();
+  }
+  input.push('\n');
+
+  let cats = ["seed", "soil", "fertilizer", "water", "light", "temperature", "humidity", "location"];
+  for pair in cats.windows(2) {
+    write!(input, "\n{}-to-{} map:\n", pair[0], pair[1]).unwrap();
+    let mut bounds = BTreeSet::new();
+    while bounds.len() < 2 * size {
+      bounds.insert(rng.gen_range(0..LIMIT));
+    }
+    let bounds: Vec<_> = bounds.into_iter().collect();
+    let mut entries: Vec<_> = bounds.chunks(2)
+      .map(|pair| (rng.gen_range(0..LIMIT), pair[0], pair[1] - pair[0]))
+      .collect();
+    entries.shuffle(&mut rng);
+    for (dest, src, len) in entries {
+      writeln!(input, "{} {} {}", dest, src, len).unwrap();
+    }
+  }
+  input
+}
+
+// `size` ghosts, each walking its own ring of nodes whose length is a distinct prime, so that the cycle lengths are
+// large and coprime. Each ring's start is its first node and its end is its last, and the first ring runs from AAA to
+// ZZZ for part 1. All the ghosts stand on ends together one step before the product of the primes, which is the
+// returned part 2 answer if it fits in one. The primes are drawn from those between 50 and 1000, or further up once
+// `size` needs more than there are
+pub fn day08(size: usize, seed: u64) -> (String, Result<Answer>) {
+  let mut rng = StdRng::seed_from_u64(seed);
+  let size = size.max(1);
+  let mut primes = vec![];
+  for n in 50.. {
+    if n >= 1000 && primes.len() >= size { break; }
+    if (2..n).take_while(|d| d * d <= n).all(|d| n % d != 0) { primes.push(n); }
+  }
+  primes.shuffle(&mut rng);
+  primes.truncate(size);
+
+  // Names are random capitals before a last letter that marks starts and ends, with room for every node
+  let len = (2..).find(|&len| 26usize.pow(len) >= 4 * primes.iter().sum::<usize>()).unwrap();
+  let mut seen: HashSet<String> = ["AAA".to_owned(), "ZZZ".to_owned()].into();
+  let mut name = |rng: &mut StdRng, last: char| loop {
+    let mut name: String = (0..len).map(|_| rng.gen_range('A'..='Z')).collect();
+    name.push(last);
+    if seen.insert(name.clone()) { break name; }
+  };
+  let mut lines = vec![];
+  let mut steps = BigUint::one();
+  for (i, &len) in primes.iter().enumerate() {
+    let ring: Vec<_> = (0..len)
+      .map(|n| match n {
+        0 if i == 0 => "AAA".to_owned(),
+        0 => name(&mut rng, 'A'),
+        _ if n == len - 1 && i == 0 => "ZZZ".to_owned(),
+        _ if n == len - 1 => name(&mut rng, 'Z'),
+        _ => {
+          let last = rng.gen_range('B'..='Y');
+          name(&mut rng, last)
+        }
+      })
+      .collect();
+    for (n, node) in ring.iter().enumerate() {
+      let next = &ring[(n + 1) % len];
+      lines.push(format!("{} = ({}, {})\n", node, next, next));
+    }
+    steps *= len;
+  }
+  lines.shuffle(&mut rng);
+
+  let route: String = (0..rng.gen_range(5..50)).map(|_| if rng.gen() { 'L' } else { 'R' }).collect();
+  (format!("{}\n\n{}", route, lines.concat()), (steps - 1u32).try_into())
+}
+
+// `size` workflows, each only sending parts to workflows listed after it so that every part is eventually accepted
+// or rejected, and `size` parts
+pub fn day19(size: usize, seed: u64) -> String {
+  let mut rng = StdRng::seed_from_u64(seed);
+  let size = size.max(1);
+  let mut flows = vec!["in".to_owned()];
+  flows.extend(names(&mut rng, size - 1, &["in"]));
+
+  let mut input = String::new();
+  for (i, name) in flows.iter().enumerate() {
+    let dest = |rng: &mut StdRng| match rng.gen_range(i..flows.len() + 2) {
+      n if n == flows.len() => "A".to_owned(),
+      n if n == flows.len() + 1 || n == i => "R".to_owned(),
+      n => flows[n].clone(),
+    };
+    write!(input, "{}{{", name).unwrap();
+    for _ in 0..rng.gen_range(1..=4) {
+      let qual = *b"xmas".choose(&mut rng).unwrap() as char;
+      let op = if rng.gen() { '<' } else { '>' };
+      write!(input, "{}{}{}:{},", qual, op, rng.gen_range(1..=4000), dest(&mut rng)).unwrap();
+    }
+    writeln!(input, "{}}}", dest(&mut rng)).unwrap();
+  }
+  input.push('\n');
+  for _ in 0..size {
+    let [x, m, a, s] = [(); 4].map(|_| rng.gen_range(1..=4000));
+    writeln!(input, "{{x={},m={},a={},s={}}}", x, m, a, s).unwrap();
+  }
+  input
+}
+
+// Like the real input, `size` 12-bit counters of flip-flops, each resetting itself through a conjunction when it reaches
+// a random target. rx receives a low pulse once all counters reset on the same press, which is the returned answer if it
+// fits in one
+pub fn day20(size: usize, seed: u64) -> (String, Result<Answer>) {
+  const BITS: usize = 12;
+  let mut rng = StdRng::seed_from_u64(seed);
+  let size = size.max(1);
+  let mut names = names(&mut rng, size * (BITS + 2) + 1, &["rx"]).into_iter();
+  let fin = names.next().unwrap();
+
+  let mut lines = vec![];
+  let mut starts = vec![];
+  let mut presses = BigUint::one();
+  for _ in 0..size {
+    let bits: Vec<_> = names.by_ref().take(BITS).collect();
+    let (hub, inv) = (names.next().unwrap(), names.next().unwrap());
+    // The lowest and highest bits are always set
+    let target: u32 = (rng.gen_range(0..1 << (BITS - 2)) << 1) | 1 | (1 << (BITS - 1));
+    presses = presses.lcm(&target.into());
+
+    let mut hub_dests = vec![bits[0].clone()];
+    for (i, bit) in bits.iter().enumerate() {
+      let mut dests: Vec<_> = bits.get(i + 1).into_iter().cloned().collect();
+      if target & (1 << i) != 0 {
+        dests.push(hub.clone());
+      } else {
+        hub_dests.push(bit.clone());
+      }
+      dests.shuffle(&mut rng);
+      lines.push(format!("%{} -> {}", bit, dests.join(", ")));
+    }
+    hub_dests.push(inv.clone());
+    hub_dests.shuffle(&mut rng);
+    lines.push(format!("&{} -> {}", hub, hub_dests.join(", ")));
+    lines.push(format!("&{} -> {}", inv, fin));
+    starts.push(bits[0].clone());
+  }
+  lines.push(format!("broadcaster -> {}", starts.join(", ")));
+  lines.push(format!("&{} -> rx", fin));
+  lines.shuffle(&mut rng);
+
+  (lines.into_iter().map(|line| line + "\n").collect(), presses.try_into())
+}
+
+// `size` non-overlapping bricks of up to four cubes in a 10x10 column
+pub fn day22(size: usize, seed: u64) -> String {
+  let mut rng = StdRng::seed_from_u64(seed);
+  let height = size.max(1);
+  let mut filled = HashSet::new();
+  let mut input = String::new();
+  let mut placed = 0;
+  while placed < size {
+    let start = [rng.gen_range(0..10), rng.gen_range(0..10), rng.gen_range(1..=height)];
+    let axis = rng.gen_range(0..3);
+    let mut end = start;
+    end[axis] += rng.gen_range(0..4);
+    if end[0] >= 10 || end[1] >= 10 { continue; }
+    let cubes: Vec<_> = (start[axis]..=end[axis])
+      .map(|n| { let mut cube = start; cube[axis] = n; cube })
+      .collect();
+    if cubes.iter().any(|cube| filled.contains(cube)) { continue; }
+    filled.extend(cubes);
+    writeln!(input, "{},{},{}~{},{},{}", start[0], start[1], start[2], end[0], end[1], end[2]).unwrap();
+    placed += 1;
+  }
+  input
+}
+
+// `size` hailstones, at least three, that a rock thrown from a random position hits at distinct whole times. The
+// times are small so that the solver's search over hit times stays short. Returns the part 2 answer with the input
+pub fn day24(size: usize, seed: u64) -> (String, Answer) {
+  let mut rng = StdRng::seed_from_u64(seed);
+  let size = size.max(3);
+  let rock_pos = [(); 3].map(|_| rng.gen_range(100_000_000_000_000..400_000_000_000_000i64));
+  let rock_vel = [(); 3].map(|_| rng.gen_range(-300..=300i64));
+
+  let mut times: Vec<i64> = (1..=10 * size as i64).collect();
+  times.shuffle(&mut rng);
+  let mut input = String::new();
+  for &t in &times[..size] {
+    let vel = loop {
+      let vel = [(); 3].map(|_| rng.gen_range(-300..=300i64));
+      if vel != rock_vel { break vel; }
+    };
+    let pos: Vec<_> = (0..3).map(|i| rock_pos[i] + t * (rock_vel[i] - vel[i])).collect();
+    writeln!(input, "{}, {}, {} @ {}, {}, {}", pos[0], pos[1], pos[2], vel[0], vel[1], vel[2]).unwrap();
+  }
+  (input, rock_pos.into_iter().sum::<i64>().into())
+}
+
+// Two well-connected groups of between `size` and half as many again components each (at least eight), joined by
+// exactly three wires. Returns the part 1 answer with the input
+pub fn day25(size: usize, seed: u64) -> (String, Answer) {
+  let mut rng = StdRng::seed_from_u64(seed);
+  let sizes = [(); 2].map(|_| rng.gen_range(size.max(8)..=size.max(8) * 3 / 2));
+  let mut names = names(&mut rng, sizes[0] + sizes[1], &[]);
+  names.shuffle(&mut rng);
+  let (left, right) = names.split_at(sizes[0]);
+
+  // Wiring each component to its three neighbours either way round a ring means it takes six cuts to split it
+  let mut wires = BTreeSet::new();
+  for group in [left, right] {
+    for i in 0..group.len() {
+      for off in 1..=3 {
+        let (a, b) = (&group[i], &group[(i + off) % group.len()]);
+        wires.insert((a.min(b), a.max(b)));
+      }
+    }
+  }
+  while wires.len() < (sizes[0] + sizes[1]) * 3 + 3 {
+    let (a, b) = (left.choose(&mut rng).unwrap(), right.choose(&mut rng).unwrap());
+    wires.insert((a.min(b), a.max(b)));
+  }
+
+  let mut listed: BTreeMap<_, Vec<_>> = BTreeMap::new();
+  for (a, b) in wires {
+    let (a, b) = if rng.gen() { (a, b) } else { (b, a) };
+    listed.entry(a).or_default().push(b);
+  }
+  let mut lines: Vec<_> = listed.into_iter()
+    .map(|(a, others)| format!("{}: {}\n", a, others.iter().join(" ")))
+    .collect();
+  lines.shuffle(&mut rng);
+  (lines.concat(), (sizes[0] * sizes[1]).into())
+}
+
+// Distinct lowercase names, at least three letters long like the real inputs
+fn names(rng: &mut StdRng, count: usize, reserved: &[&str]) -> Vec<String> {
+  let len = (3..).find(|&len| 26usize.pow(len) >= 4 * count).unwrap();
+  let mut seen: HashSet<String> = reserved.iter().map(|&name| name.to_owned()).collect();
+  let mut names = vec![];
+  while names.len() < count {
+    let name: String = (0..len).map(|_| rng.gen_range('a'..='z')).collect();
+    if seen.insert(name.clone()) {
+      names.push(name);
+    }
+  }
+  names
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{solution, error::Error};
+
+  #[test]
+  fn test_parse() {
+    for day in [4, 5, 8, 19, 20, 22, 24, 25] {
+      for seed in 0..5 {
+        let input = generate(day, 20, seed).unwrap();
+        assert_eq!(input, generate(day, 20, seed).unwrap());
+        assert!(solution(day).unwrap().parse(&input).is_ok(), "day {} seed {}:\n{}", day, seed, input);
+      }
+    }
+    assert!(generate(1, 20, 0).is_none());
+  }
+
+  #[test]
+  fn test_day08_sizes() {
+    // More ghosts than there are primes below 1000
+    let (input, _) = day08(200, 0);
+    let starts = input.lines().filter(|line| line.split(' ').next().is_some_and(|node| node.ends_with('A'))).count();
+    assert_eq!(starts, 200);
+    assert!(solution(8).unwrap().parse(&input).is_ok());
+  }
+
+  #[test]
+  fn test_known_answers() {
+    for seed in 0..3 {
+      let (input, answer) = day24(5, seed);
+      assert_eq!(solution(24).unwrap().run(&input, 2, &[]), Ok(answer));
+      let (input, answer) = day25(12, seed);
+      assert_eq!(solution(25).unwrap().run(&input, 1, &[]), Ok(answer));
+      let (input, answer) = day08(5, seed);
+      assert_eq!(solution(8).unwrap().run(&input, 2, &[]), answer);
+      let (input, answer) = day08(20, seed);
+      assert!(matches!(answer, Err(Error::Overflow(_))));
+      assert_eq!(solution(8).unwrap().run(&input, 2, &[]), answer);
+      let (input, answer) = day20(3, seed);
+      assert!(answer.is_ok());
+      assert_eq!(solution(20).unwrap().run(&input, 2, &[]), answer);
+      let (input, answer) = day20(20, seed);
+      assert!(matches!(answer, Err(Error::Overflow(_))));
+      assert_eq!(solution(20).unwrap().run(&input, 2, &[]), answer);
+    }
+  }
+}
@@ -1,10 +1,10 @@
-#![feature(btree_cursors)]
-#![feature(exact_size_is_empty)]
-#![feature(is_sorted)]
-#![feature(let_chains)]
-#![feature(map_try_insert)]
+#![cfg_attr(feature = "nightly", feature(btree_cursors, map_try_insert))]
 
 mod util;
+pub mod error;
+pub mod solution;
+pub mod manifest;
+pub mod generate;
 
 #[cfg(test)]
 mod test_util;
@@ -34,3 +34,37 @@ pub mod day22;
 pub mod day23;
 pub mod day24;
 pub mod day25;
+
+use solution::{DynSolution, Registered};
+
+pub static SOLUTIONS: [&dyn DynSolution; 25] = [
+  &Registered::<day01::Solver>::new(),
+  &Registered::<day02::Solver>::new(),
+  &Registered::<day03::Solver>::new(),
+  &Registered::<day04::Solver>::new(),
+  &Registered::<day05::Solver>::new(),
+  &Registered::<day06::Solver>::new(),
+  &Registered::<day07::Solver>::new(),
+  &Registered::<day08::Solver>::new(),
+  &Registered::<day09::Solver>::new(),
+  &Registered::<day10::Solver>::new(),
+  &Registered::<day11::Solver>::new(),
+  &Registered::<day12::Solver>::new(),
+  &Registered::<day13::Solver>::new(),
+  &Registered::<day14::Solver>::new(),
+  &Registered::<day15::Solver>::new(),
+  &Registered::<day16::Solver>::new(),
+  &Registered::<day17::Solver>::new(),
+  &Registered::<day18::Solver>::new(),
+  &Registered::<day19::Solver>::new(),
+  &Registered::<day20::Solver>::new(),
+  &Registered::<day21::Solver>::new(),
+  &Registered::<day22::Solver>::new(),
+  &Registered::<day23::Solver>::new(),
+  &Registered::<day24::Solver>::new(),
+  &Registered::<day25::Solver>::new(),
+];
+
+pub fn solution(day: u8) -> Option<&'static dyn DynSolution> {
+  SOLUTIONS.get(usize::from(day).checked_sub(1)?).copied()
+}
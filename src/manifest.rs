@@ -0,0 +1,126 @@
+use std::{collections::BTreeMap, fs::read_to_string, path::{Path, PathBuf}, time::{Duration, Instant}};
+
+use serde::{Deserialize, Deserializer, de};
+
+use crate::{solution, error::{Error, Result}, solution::Answer};
+
+pub const DEFAULT_PATH: &str = "data/answers.toml";
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+  #[serde(rename = "check", default)]
+  pub checks: Vec<Check>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Check {
+  pub day: u8,
+  pub part: u8,
+  pub input: PathBuf,
+  #[serde(default)]
+  pub params: BTreeMap<String, String>,
+  #[serde(deserialize_with = "answer")]
+  pub answer: Answer,
+}
+
+// TOML integers stop at 64 bits, so larger answers are written as strings
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum WrittenAnswer {
+  Integer(i64),
+  String(String),
+}
+
+fn answer<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Answer, D::Error> {
+  match WrittenAnswer::deserialize(deserializer)? {
+    WrittenAnswer::Integer(n) => Ok(Answer(n.into())),
+    WrittenAnswer::String(text) => text.parse().map(Answer)
+      .map_err(|_| de::Error::custom(format!("answer {:?} is not a 128-bit integer", text))),
+  }
+}
+
+impl Manifest {
+  // Input paths are resolved against the manifest's own directory, so a manifest can sit next to its inputs
+  pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+    let path = path.as_ref();
+    let text = read_to_string(path)
+      .map_err(|e| Error::Manifest(format!("reading {}: {}", path.display(), e)))?;
+    let mut manifest: Self = toml::from_str(&text)
+      .map_err(|e| Error::Manifest(format!("{}: {}", path.display(), e)))?;
+    let dir = path.parent().unwrap_or(Path::new(""));
+    for check in &mut manifest.checks {
+      check.input = dir.join(&check.input);
+    }
+    Ok(manifest)
+  }
+}
+
+impl Check {
+  pub fn expected(&self) -> Answer {
+    self.answer
+  }
+
+  // Sample inputs are named after the day with a letter suffix, like 05a.txt
+  pub fn is_sample(&self) -> bool {
+    self.input.file_stem()
+      .and_then(|stem| stem.to_str())
+      .is_some_and(|stem| stem.ends_with(char::is_alphabetic))
+  }
+
+  pub fn params(&self) -> Vec<(&str, &str)> {
+    self.params.iter()
+      .map(|(name, value)| (name.as_str(), value.as_str()))
+      .collect()
+  }
+
+  pub fn read_input(&self) -> Result<String> {
+    read_to_string(&self.input)
+      .map_err(|e| Error::Manifest(format!("reading {}: {}", self.input.display(), e)))
+  }
+
+  // Times parsing and solving together, but not reading the input
+  pub fn run(&self) -> Result<(Answer, Duration)> {
+    let sol = solution(self.day)
+      .ok_or_else(|| Error::Manifest(format!("no solution for day {}", self.day)))?;
+    let input = self.read_input()?;
+    let start = Instant::now();
+    let answer = sol.run(&input, self.part, &self.params())?;
+    Ok((answer, start.elapsed()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn test_load() {
+    let manifest = Manifest::load(DEFAULT_PATH).unwrap();
+    for check in &manifest.checks {
+      assert!(solution(check.day).is_some_and(|sol| check.part <= sol.parts()), "{:?}", check);
+      assert!(check.input.starts_with("data"), "{:?}", check);
+    }
+    assert!(Manifest::load("data/missing.toml").is_err());
+  }
+
+  #[test]
+  fn test_run_samples() {
+    // Sample inputs are quick enough to run here
+    let manifest = Manifest::load(DEFAULT_PATH).unwrap();
+    for check in manifest.checks.iter().filter(|check| check.is_sample()) {
+      assert_eq!(check.run().map(|(answer, _)| answer), Ok(check.expected()), "{:?}", check);
+    }
+  }
+
+  #[test]
+  fn test_parse() {
+    let manifest: Manifest = toml::from_str("[[check]]\nday = 21\npart = 1\ninput = \"21a.txt\"\nparams = { steps = \"6\" }\nanswer = 16\n").unwrap();
+    assert_eq!(manifest.checks[0].params(), [("steps", "6")]);
+    assert!(toml::from_str::<Manifest>("[[check]]\nday = 1\npart = 1\ninput = \"01a.txt\"\nanswr = 142\n").is_err());
+    let manifest: Manifest = toml::from_str("[[check]]\nday = 4\npart = 2\ninput = \"04.txt\"\nanswer = \"-170141183460469231731687303715884105728\"\n").unwrap();
+    assert_eq!(manifest.checks[0].expected(), Answer(i128::MIN));
+    assert!(toml::from_str::<Manifest>("[[check]]\nday = 4\npart = 2\ninput = \"04.txt\"\nanswer = \"12x\"\n").is_err());
+  }
+}
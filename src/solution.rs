@@ -0,0 +1,199 @@
+use std::{any::Any, fmt::{self, Display}, io::{BufRead, Cursor}, marker::PhantomData, str::FromStr};
+
+use num::{BigInt, BigUint};
+
+use crate::error::{Error, Result};
+
+pub trait Solution {
+  const DAY: u8;
+  const PARTS: u8 = 2;
+
+  type Input: 'static;
+  type Params: Params;
+
+  fn parse(input: &str) -> Result<Self::Input>;
+  // Reads all of the input before parsing any of it
+  fn read(reader: impl BufRead) -> Result<Self::Input> {
+    Self::parse(&read_input(reader)?)
+  }
+  fn part1(input: &Self::Input, params: &Self::Params) -> Result<Answer>;
+  fn part2(_input: &Self::Input, _params: &Self::Params) -> Result<Answer> {
+    Err(Error::Usage(format!("day {} has no part 2", Self::DAY)))
+  }
+}
+
+pub trait Params: Default + 'static {
+  const NAMES: &'static [&'static str] = &[];
+
+  fn set(&mut self, name: &str, value: &str) -> Result<()>;
+}
+
+impl Params for () {
+  fn set(&mut self, name: &str, _value: &str) -> Result<()> {
+    unknown_param(name)
+  }
+}
+
+// Nothing is streamed: the whole input is read into one String before parsing starts, so that parsers can borrow
+// lines from it rather than allocating each one. Memory use grows with the input
+pub fn read_input(mut reader: impl BufRead) -> Result<String> {
+  let mut input = String::new();
+  reader.read_to_string(&mut input)?;
+  Ok(input)
+}
+
+// For the entry points that still take the input as owned lines, as every day did before reading from a BufRead
+pub fn joined_lines(lines: impl Iterator<Item=String>) -> Cursor<String> {
+  Cursor::new(lines.map(|line| line + "\n").collect())
+}
+
+pub fn parse_param<T: FromStr>(name: &str, value: &str) -> Result<T> {
+  value.parse().map_err(|_| Error::Usage(format!("invalid value {:?} for parameter {:?}", value, name)))
+}
+
+// A parameter listing named values, like "red=12,green=13"
+pub fn parse_param_pairs<T: FromStr>(name: &str, value: &str) -> Result<Vec<(String, T)>> {
+  value.split(',')
+    .map(|pair| {
+      let (key, n) = pair.split_once('=')
+        .ok_or_else(|| Error::Usage(format!("expected key=value in parameter {:?}, not {:?}", name, pair)))?;
+      Ok((key.to_owned(), parse_param(name, n)?))
+    })
+    .collect()
+}
+
+pub fn unknown_param<T>(name: &str) -> Result<T> {
+  Err(Error::Usage(format!("unknown parameter {:?}", name)))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Answer(pub i128);
+
+impl Display for Answer {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.0.fmt(f)
+  }
+}
+
+macro_rules! answer_from {
+  ($($t:ty),*) => { $(
+    impl From<$t> for Answer {
+      fn from(n: $t) -> Self { Self(n.try_into().unwrap()) }
+    }
+  )* };
+}
+answer_from!(u32, u64, usize, i32, i64, isize);
+
+impl TryFrom<BigUint> for Answer {
+  type Error = Error;
+
+  fn try_from(n: BigUint) -> Result<Self> {
+    i128::try_from(&n).map(Self).map_err(|_| Error::Overflow(n.to_string()))
+  }
+}
+
+impl TryFrom<BigInt> for Answer {
+  type Error = Error;
+
+  fn try_from(n: BigInt) -> Result<Self> {
+    i128::try_from(&n).map(Self).map_err(|_| Error::Overflow(n.to_string()))
+  }
+}
+
+// Object-safe view of a Solution, so days can be stored together and driven generically
+pub trait DynSolution: Sync {
+  fn day(&self) -> u8;
+  fn parts(&self) -> u8;
+  fn param_names(&self) -> &'static [&'static str];
+  fn parse(&self, input: &str) -> Result<Box<dyn Any>>;
+  fn read(&self, reader: &mut dyn BufRead) -> Result<Box<dyn Any>>;
+  fn solve(&self, input: &dyn Any, part: u8, params: &[(&str, &str)]) -> Result<Answer>;
+
+  fn run(&self, input: &str, part: u8, params: &[(&str, &str)]) -> Result<Answer> {
+    self.solve(self.parse(input)?.as_ref(), part, params)
+  }
+}
+
+pub struct Registered<S>(PhantomData<fn() -> S>);
+
+impl<S> Registered<S> {
+  pub const fn new() -> Self { Self(PhantomData) }
+}
+
+impl<S> Default for Registered<S> {
+  fn default() -> Self { Self::new() }
+}
+
+impl<S: Solution> DynSolution for Registered<S> {
+  fn day(&self) -> u8 { S::DAY }
+
+  fn parts(&self) -> u8 { S::PARTS }
+
+  fn param_names(&self) -> &'static [&'static str] { S::Params::NAMES }
+
+  fn parse(&self, input: &str) -> Result<Box<dyn Any>> {
+    Ok(Box::new(S::parse(input)?))
+  }
+
+  fn read(&self, reader: &mut dyn BufRead) -> Result<Box<dyn Any>> {
+    Ok(Box::new(S::read(reader)?))
+  }
+
+  fn solve(&self, input: &dyn Any, part: u8, params: &[(&str, &str)]) -> Result<Answer> {
+    let input = input.downcast_ref::<S::Input>()
+      .ok_or_else(|| Error::Usage(format!("input was not parsed by day {}", S::DAY)))?;
+    let mut typed_params = S::Params::default();
+    for &(name, value) in params {
+      typed_params.set(name, value)?;
+    }
+    match part {
+      1 => S::part1(input, &typed_params),
+      2 => S::part2(input, &typed_params),
+      _ => Err(Error::Usage(format!("day {} has no part {}", S::DAY, part))),
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{SOLUTIONS, solution, test_util::{sample_file, sample_input, sample_lines}};
+
+  #[test]
+  fn test_registry() {
+    assert!(SOLUTIONS.iter().map(|s| s.day()).eq(1..=25));
+    assert!(solution(0).is_none());
+    assert!(solution(26).is_none());
+  }
+
+  #[test]
+  fn test_run() {
+    assert_eq!(solution(5).unwrap().run(&sample_file("05a"), 2, &[]), Ok(Answer(46)));
+    assert_eq!(solution(25).unwrap().run(&sample_file("25a"), 2, &[]), Err(Error::Usage("day 25 has no part 2".to_string())));
+    assert!(matches!(solution(5).unwrap().run("seeds: 1\n", 1, &[]), Err(Error::Parse(_))));
+  }
+
+  #[test]
+  fn test_params() {
+    assert_eq!(solution(21).unwrap().run(&sample_file("21a"), 1, &[("steps", "6")]), Ok(Answer(16)));
+    assert_eq!(solution(24).unwrap().run(&sample_file("24a"), 1, &[("test_range", "7,27")]), Ok(Answer(2)));
+    assert!(solution(21).unwrap().run(&sample_file("21a"), 1, &[("stpes", "6")]).is_err());
+  }
+
+  #[test]
+  fn test_read() {
+    let sol = solution(5).unwrap();
+    let input = sol.read(&mut sample_input("05a")).unwrap();
+    assert_eq!(sol.solve(input.as_ref(), 2, &[]), Ok(Answer(46)));
+    let err = sol.read(&mut &b"seeds: \xff\n"[..]).unwrap_err();
+    assert_eq!(err.to_string(), "reading input: stream did not contain valid UTF-8");
+  }
+
+  #[test]
+  fn test_old_entry_points() {
+    assert_eq!(crate::day02::part1_lines(sample_lines("02a")), Ok(8));
+    assert_eq!(crate::day21::part1_lines(sample_lines("21a"), 6), Ok(16));
+    assert_eq!(crate::day13::part1_file(sample_file("13a")), Ok(405));
+    assert_eq!(joined_lines(["a".to_owned(), "b".to_owned()].into_iter()).into_inner(), "a\nb\n");
+  }
+}
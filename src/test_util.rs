@@ -1,10 +1,12 @@
 use std::io::{BufRead, BufReader};
 use std::fs::{File, read_to_string};
 
-pub fn sample_lines(id: &str) -> impl Iterator<Item=String> {
+pub fn sample_input(id: &str) -> BufReader<File> {
   BufReader::new(File::open(sample_filename(id)).unwrap())
-    .lines()
-    .map(Result::unwrap)
+}
+
+pub fn sample_lines(id: &str) -> impl Iterator<Item=String> {
+  sample_input(id).lines().map(Result::unwrap)
 }
 
 pub fn sample_file(id: &str) -> String {
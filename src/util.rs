@@ -1,4 +1,15 @@
+use std::{collections::{BTreeMap, HashMap, btree_map}, hash::{BuildHasher, Hash}, ops::{Index, IndexMut}};
+#[cfg(not(feature = "nightly"))]
+use std::collections::hash_map::Entry;
+#[cfg(feature = "nightly")]
+use std::ops::Bound;
+
+use enum_map::Enum;
+use itertools::iproduct;
 use nom::{IResult, character::complete::{i64, u64}, combinator::map};
+use strum::{EnumIter, IntoEnumIterator};
+
+use crate::error::{Error, Result};
 
 pub fn usize(input: &str) -> IResult<&str, usize> {
   map(
@@ -14,4 +25,261 @@ pub fn isize(input: &str) -> IResult<&str, isize> {
   )(input)
 }
 
+// Stable equivalent of HashMap::try_insert. On an occupied key, hands back the existing value and the rejected one
+pub trait TryInsert<K, V> {
+  fn insert_vacant(&mut self, key: K, value: V) -> std::result::Result<&mut V, (&mut V, V)>;
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> TryInsert<K, V> for HashMap<K, V, S> {
+  #[cfg(feature = "nightly")]
+  fn insert_vacant(&mut self, key: K, value: V) -> std::result::Result<&mut V, (&mut V, V)> {
+    self.try_insert(key, value).map_err(|e| (e.entry.into_mut(), e.value))
+  }
+
+  #[cfg(not(feature = "nightly"))]
+  fn insert_vacant(&mut self, key: K, value: V) -> std::result::Result<&mut V, (&mut V, V)> {
+    match self.entry(key) {
+      Entry::Occupied(e) => Err((e.into_mut(), value)),
+      Entry::Vacant(e) => Ok(e.insert(value)),
+    }
+  }
+}
+
+// Iterates from the last entry at or below key, or from the first entry if there is none. Equivalent to walking forward from BTreeMap::upper_bound
+pub fn range_from_floor<'a, K: Ord + Copy, V>(map: &'a BTreeMap<K, V>, key: K) -> btree_map::Range<'a, K, V> {
+  #[cfg(feature = "nightly")]
+  let floor = map.upper_bound(Bound::Included(&key)).peek_prev().map(|(&k, _)| k);
+  #[cfg(not(feature = "nightly"))]
+  let floor = map.range(..=key).next_back().map(|(&k, _)| k);
+  map.range(floor.unwrap_or(key)..)
+}
+
 pub type Coord = (usize, usize);
+
+#[derive(Enum, EnumIter, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Debug, Hash, Default)]
+pub enum Dir { #[default] E, N, W, S }
+
+impl Dir {
+  pub fn turn_ccw(self) -> Self {
+    match self {
+      Self::E => Self::N,
+      Self::N => Self::W,
+      Self::W => Self::S,
+      Self::S => Self::E,
+    }
+  }
+
+  pub fn turn_cw(self) -> Self {
+    match self {
+      Self::E => Self::S,
+      Self::N => Self::E,
+      Self::W => Self::N,
+      Self::S => Self::W,
+    }
+  }
+
+  pub fn reverse(self) -> Self {
+    self.turn_cw().turn_cw()
+  }
+
+  // Step from pos, staying within a grid of the given size
+  pub fn try_step(self, (y, x): Coord, (max_y, max_x): Coord) -> Option<Coord> {
+    match self {
+      Self::E => x.checked_add(1).map(|x| (y, x)).filter(|&(_, x)| x < max_x),
+      Self::N => y.checked_sub(1).map(|y| (y, x)),
+      Self::W => x.checked_sub(1).map(|x| (y, x)),
+      Self::S => y.checked_add(1).map(|y| (y, x)).filter(|&(y, _)| y < max_y),
+    }
+  }
+}
+
+// A rectangular grid stored row by row, indexed by (y, x)
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct Grid<T> {
+  cells: Vec<T>,
+  width: usize,
+}
+
+impl<T> Grid<T> {
+  pub fn new((height, width): Coord, value: T) -> Self where T: Clone {
+    Self { cells: vec![value; height * width], width }
+  }
+
+  // None if the rows are not all the same length
+  pub fn from_rows<R: IntoIterator<Item=T>>(rows: impl IntoIterator<Item=R>) -> Option<Self> {
+    let mut cells = vec![];
+    let mut width = None;
+    for row in rows {
+      let row_start = cells.len();
+      cells.extend(row);
+      if *width.get_or_insert(cells.len() - row_start) != cells.len() - row_start {
+        return None;
+      }
+    }
+    Some(Self { cells, width: width.unwrap_or_default() })
+  }
+
+  // Parse a map with one char per tile, rejecting chars `tile` doesn't recognise, ragged rows and empty maps
+  pub fn parse(day: u8, lines: impl Iterator<Item=impl AsRef<str>>, mut tile: impl FnMut(char) -> Option<T>) -> Result<Self> {
+    let mut cells = vec![];
+    let mut width = None;
+    for (y, line) in lines.enumerate() {
+      let line = line.as_ref();
+      let row_start = cells.len();
+      for (x, c) in line.chars().enumerate() {
+        cells.push(tile(c).ok_or_else(|| Error::parse(day, y, line, x, "unexpected tile"))?);
+      }
+      if *width.get_or_insert(cells.len() - row_start) != cells.len() - row_start {
+        return Err(Error::parse(day, y, line, 0, "row length differs from the first row"));
+      }
+    }
+    match width {
+      Some(width) if width > 0 => Ok(Self { cells, width }),
+      _ => Err(Error::input(day, "empty map")),
+    }
+  }
+
+  pub fn height(&self) -> usize {
+    self.cells.len().checked_div(self.width).unwrap_or(0)
+  }
+
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  pub fn size(&self) -> Coord {
+    (self.height(), self.width)
+  }
+
+  pub fn get(&self, (y, x): Coord) -> Option<&T> {
+    if x < self.width { self.cells.get(y * self.width + x) } else { None }
+  }
+
+  pub fn get_mut(&mut self, (y, x): Coord) -> Option<&mut T> {
+    if x < self.width { self.cells.get_mut(y * self.width + x) } else { None }
+  }
+
+  pub fn step(&self, pos: Coord, dir: Dir) -> Option<Coord> {
+    dir.try_step(pos, self.size())
+  }
+
+  pub fn neighbours(&self, pos: Coord) -> impl Iterator<Item=(Dir, Coord)> + '_ {
+    Dir::iter().filter_map(move |dir| Some((dir, self.step(pos, dir)?)))
+  }
+
+  pub fn neighbours8(&self, (y, x): Coord) -> impl Iterator<Item=Coord> + '_ {
+    iproduct!(y.saturating_sub(1)..=y+1, x.saturating_sub(1)..=x+1)
+      .filter(move |&(ny, nx)| (ny, nx) != (y, x) && ny < self.height() && nx < self.width)
+  }
+
+  pub fn row(&self, y: usize) -> &[T] {
+    &self.cells[y * self.width..(y + 1) * self.width]
+  }
+
+  pub fn row_mut(&mut self, y: usize) -> &mut [T] {
+    &mut self.cells[y * self.width..(y + 1) * self.width]
+  }
+
+  pub fn rows(&self) -> impl Iterator<Item=&[T]> {
+    self.cells.chunks(self.width.max(1))
+  }
+
+  pub fn col(&self, x: usize) -> impl DoubleEndedIterator<Item=&T> {
+    self.cells.iter().skip(x).step_by(self.width.max(1))
+  }
+
+  pub fn positions(&self) -> impl Iterator<Item=Coord> {
+    iproduct!(0..self.height(), 0..self.width)
+  }
+
+  pub fn iter(&self) -> impl Iterator<Item=(Coord, &T)> {
+    self.positions().zip(&self.cells)
+  }
+
+  pub fn map<U>(&self, f: impl FnMut(&T) -> U) -> Grid<U> {
+    Grid { cells: self.cells.iter().map(f).collect(), width: self.width }
+  }
+}
+
+impl<T: Clone> Grid<T> {
+  pub fn transpose(&self) -> Self {
+    Self { cells: (0..self.width).flat_map(|x| self.col(x).cloned()).collect(), width: self.height() }
+  }
+
+  pub fn rotate_cw(&self) -> Self {
+    Self { cells: (0..self.width).flat_map(|x| self.col(x).rev().cloned()).collect(), width: self.height() }
+  }
+
+  pub fn rotate_ccw(&self) -> Self {
+    Self { cells: (0..self.width).rev().flat_map(|x| self.col(x).cloned()).collect(), width: self.height() }
+  }
+}
+
+impl<T> Index<Coord> for Grid<T> {
+  type Output = T;
+
+  fn index(&self, (y, x): Coord) -> &T {
+    assert!(x < self.width, "column {} out of bounds", x);
+    &self.cells[y * self.width + x]
+  }
+}
+
+impl<T> IndexMut<Coord> for Grid<T> {
+  fn index_mut(&mut self, (y, x): Coord) -> &mut T {
+    assert!(x < self.width, "column {} out of bounds", x);
+    &mut self.cells[y * self.width + x]
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn sample() -> Grid<char> {
+    Grid::parse(0, ["abc", "def"].into_iter(), Some).unwrap()
+  }
+
+  #[test]
+  fn test_parse() {
+    let grid = sample();
+    assert_eq!(grid.size(), (2, 3));
+    assert_eq!(grid[(1, 0)], 'd');
+    assert_eq!(grid.get((0, 3)), None);
+    assert!(grid.rows().eq([&['a', 'b', 'c'][..], &['d', 'e', 'f']]));
+    assert!(grid.col(1).eq(&['b', 'e']));
+    let err = Grid::parse(4, ["..", ".#"].into_iter(), |c| (c == '.').then_some(())).unwrap_err();
+    assert_eq!(err.to_string(), "day 4, line 2, column 2: unexpected tile at \"#\"");
+    let err = Grid::parse(4, ["..", "..."].into_iter(), Some).unwrap_err();
+    assert_eq!(err.to_string(), "day 4, line 2, column 1: row length differs from the first row at \"...\"");
+    assert_eq!(Grid::parse(4, [""].into_iter(), Some).unwrap_err().to_string(), "day 4: empty map");
+    assert_eq!(Grid::from_rows([vec![1, 2], vec![3]]), None);
+  }
+
+  #[test]
+  fn test_neighbours() {
+    let grid = sample();
+    assert!(grid.neighbours((0, 0)).eq([(Dir::E, (0, 1)), (Dir::S, (1, 0))]));
+    assert!(grid.neighbours((1, 1)).map(|(dir, _)| dir).eq([Dir::E, Dir::N, Dir::W]));
+    assert!(grid.neighbours8((0, 1)).eq([(0, 0), (0, 2), (1, 0), (1, 1), (1, 2)]));
+  }
+
+  #[test]
+  fn test_rearrange() {
+    let grid = sample();
+    assert_eq!(grid.transpose(), Grid::from_rows(["ad".chars(), "be".chars(), "cf".chars()]).unwrap());
+    assert_eq!(grid.rotate_cw(), Grid::from_rows(["da".chars(), "eb".chars(), "fc".chars()]).unwrap());
+    assert_eq!(grid.rotate_ccw(), Grid::from_rows(["cf".chars(), "be".chars(), "ad".chars()]).unwrap());
+    assert_eq!(grid.rotate_cw().rotate_cw().rotate_cw().rotate_cw(), grid);
+    assert_eq!(grid.map(|c| c.to_ascii_uppercase())[(0, 2)], 'C');
+  }
+
+  #[test]
+  fn test_dir() {
+    for dir in Dir::iter() {
+      assert_eq!(dir.turn_cw().turn_ccw(), dir);
+      assert_eq!(dir.reverse().reverse(), dir);
+    }
+    assert_eq!(Dir::N.try_step((0, 0), (1, 1)), None);
+    assert_eq!(Dir::S.try_step((0, 0), (2, 1)), Some((1, 0)));
+  }
+}